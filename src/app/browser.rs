@@ -1,4 +1,4 @@
-use crate::model::entry::{DirEntry, FileEntry};
+use ark_tagger::model::entry::{DirEntry, FileEntry, FileKind};
 use super::message::{BrowserMessage, DirMessage, FileMessage};
 use super::order::Order;
 
@@ -21,12 +21,13 @@ pub struct Browser {
     file_widgets: Vec<FileWidget>,
     dir_widgets: Vec<DirWidget>,
     asc_button: Option<button::State>,
+    show_hidden: bool,
     dir_scroll: scrollable::State,
     file_scroll: scrollable::State,
 }
 
 impl Browser {
-    pub fn new<'a, F>(directories: &Vec<DirEntry>, files: F, allow_ascend: bool) -> Self
+    pub fn new<'a, F>(directories: &Vec<DirEntry>, files: F, allow_ascend: bool, show_hidden: bool) -> Self
     where F: Iterator<Item = &'a FileEntry> {
         let asc_button = if allow_ascend {
             Some(button::State::new())
@@ -49,16 +50,21 @@ impl Browser {
             file_widgets,
             dir_widgets,
             asc_button,
+            show_hidden,
             dir_scroll: scrollable::State::new(),
             file_scroll: scrollable::State::new(),
         }
     }
 
+    pub fn with_order(self, order: Order) -> Self {
+        Browser { order, ..self }
+    }
+
     pub fn refresh<'a, F>(&mut self, files: F)
         where F: Iterator<Item = &'a FileEntry> {
 
         if self.order != Order::AsIs {
-            println!("\tBuffering and ordering entries");
+            ark_tagger::trace!("\tBuffering and ordering entries");
 
             let mut files: Vec<(usize, &FileEntry)> = files.enumerate().collect();
             files.sort_by_key(|(_, file)| match self.order {
@@ -82,10 +88,19 @@ impl Browser {
         };
 
         self.file_scroll = scrollable::State::new();
+
+        //file widgets are re-created unselected
+        self.selection.clear();
+    }
+
+    pub fn refresh_directories(&mut self, directories: &Vec<DirEntry>) {
+        self.dir_widgets = directories.iter()
+            .map(|e| DirWidget::new(e))
+            .collect();
     }
 
     pub fn update(&mut self, msg: BrowserMessage) {
-        println!("\tBrowserMessage: {:?}", &msg);
+        ark_tagger::trace!("\tBrowserMessage: {:?}", &msg);
         match msg {
             BrowserMessage::FileMessage(i, msg) => {
                 match &msg {
@@ -93,7 +108,7 @@ impl Browser {
                     FileMessage::Selected(false) => { self.selection.remove(&i); }
                     _ => {}
                 };
-                println!("[ Paths selected: {:?} ]", &self.selection);
+                ark_tagger::trace!("[ Paths selected: {:?} ]", &self.selection);
 
                 if let Some(file_widget) = self.file_widgets.get_mut(i) {
                     file_widget.update(msg);
@@ -115,6 +130,7 @@ impl Browser {
                 file_widgets,
                 dir_widgets,
                 asc_button,
+                show_hidden,
                 dir_scroll,
                 file_scroll,
             } => {
@@ -148,14 +164,19 @@ impl Browser {
                         BrowserMessage::OrderSelected))
                 });
 
-                let mut column = Column::new();
+                let mut controls = Row::new();
 
                 if let Some(state) = asc_button {
-                    column = column.push(
+                    controls = controls.push(
                         Button::new(state, Text::new("up"))
                             .on_press(BrowserMessage::AscendActivated));
                 }
-                column
+                controls = controls.push(
+                    Checkbox::new(*show_hidden, "show hidden",
+                        BrowserMessage::HiddenToggled));
+
+                Column::new()
+                    .push(controls)
                     .push(directories)
                     .push(order_selector)
                     .push(files)
@@ -196,7 +217,7 @@ struct DirWidget {
 impl DirWidget {
     fn new(entry: &DirEntry) -> Self {
         DirWidget {
-            name: entry.name.clone(),
+            name: entry.name.to_string_lossy().into_owned(),
             descend_button: button::State::new(),
         }
     }
@@ -236,11 +257,19 @@ impl FileWidget {
             }
         };
 
-        let meta = format!("size: {}\nmodified: {}",
+        let mut meta = format!("size: {}\nmodified: {}",
            size, date.format("%d/%m/%Y %T"));
+        if let Some(mime) = &entry.mime {
+            meta.push_str(&format!("\ntype: {}", mime));
+        }
+        match entry.kind {
+            FileKind::Regular => {},
+            FileKind::Link => meta.push_str("\nsymbolic link"),
+            FileKind::Special => meta.push_str("\nspecial file, not indexed")
+        }
 
         FileWidget {
-            name: entry.name.clone(),
+            name: entry.name.to_string_lossy().into_owned(),
             meta,
             selected: false,
             open_button: button::State::new(),
@@ -0,0 +1,59 @@
+use ark_tagger::ipc::{Client, Event};
+
+use iced::Subscription;
+use iced_native::subscription::Recipe;
+use futures::channel::mpsc;
+use futures::stream::BoxStream;
+
+use std::path::PathBuf;
+use std::hash::{Hash, Hasher};
+use std::any::TypeId;
+use std::thread;
+
+//changes made by any client of the daemon, including this one
+pub fn events(socket: PathBuf) -> Subscription<Event> {
+    Subscription::from_recipe(Events { socket })
+}
+
+pub struct Events {
+    socket: PathBuf
+}
+
+impl<H, I> Recipe<H, I> for Events
+where H: Hasher {
+    type Output = Event;
+
+    fn hash(&self, state: &mut H) {
+        TypeId::of::<Self>().hash(state);
+        self.socket.hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: BoxStream<'static, I>) -> BoxStream<'static, Self::Output> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        let socket = self.socket;
+        thread::spawn(move || {
+            let events = match Client::connect(&socket).and_then(Client::subscribe) {
+                Ok(events) => events,
+                Err(error) => {
+                    println!("WARNING: {}", error);
+                    return;
+                }
+            };
+
+            for event in events {
+                match event {
+                    Ok(event) => if sender.unbounded_send(event).is_err() {
+                        return;
+                    },
+                    Err(error) => {
+                        println!("WARNING: {}", error);
+                        return;
+                    }
+                }
+            }
+        });
+
+        Box::pin(receiver)
+    }
+}
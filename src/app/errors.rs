@@ -0,0 +1,53 @@
+use ark_tagger::error::Error;
+use super::message::ErrorPanelMessage;
+
+use iced::{Element, Row, Column, Length, Button, Text, Color, button};
+
+//recoverable errors are shown to the user instead of aborting
+pub struct ErrorPanel {
+    messages: Vec<String>,
+    clear_button: button::State,
+}
+
+impl ErrorPanel {
+    pub fn new() -> Self {
+        ErrorPanel {
+            messages: vec![],
+            clear_button: button::State::new(),
+        }
+    }
+
+    pub fn push(&mut self, error: Error) {
+        println!("ERROR: {}", error);
+        self.messages.push(error.to_string());
+    }
+
+    pub fn update(&mut self, msg: ErrorPanelMessage) {
+        match msg {
+            ErrorPanelMessage::Cleared => self.messages.clear()
+        }
+    }
+
+    pub fn view(&mut self) -> Element<ErrorPanelMessage> {
+        if self.messages.is_empty() {
+            return Column::new().into();
+        }
+
+        let messages = self.messages.iter()
+            .fold(Column::new(), |column, message| {
+                column.push(Text::new(message)
+                    .size(12)
+                    .color(Color::from_rgb(0.8, 0.0, 0.0)))
+            })
+            .width(Length::Fill);
+
+        let button =
+            Button::new(&mut self.clear_button, Text::new("clear"))
+                .on_press(ErrorPanelMessage::Cleared);
+
+        Row::new()
+            .push(messages)
+            .push(button)
+            .into()
+    }
+}
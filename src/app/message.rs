@@ -1,10 +1,20 @@
 use super::order::Order;
+use ark_tagger::ipc::Event;
+
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub enum Message {
     TaggerMessage(TaggerMessage),
     SelectorMessage(SelectorMessage),
     BrowserMessage(BrowserMessage),
+    WatcherMessage(WatcherMessage),
+    MigratorMessage(MigratorMessage),
+    ErrorPanelMessage(ErrorPanelMessage),
+    SwitcherMessage(SwitcherMessage),
+    //changes in the directory of the configuration file
+    ConfigMessage(WatcherMessage),
+    DaemonMessage(Event),
 }
 
 #[derive(Debug, Clone)]
@@ -18,7 +28,8 @@ pub enum BrowserMessage {
     AscendActivated,
     DirMessage(usize, DirMessage),
     FileMessage(usize, FileMessage),
-    OrderSelected(Order)
+    OrderSelected(Order),
+    HiddenToggled(bool)
 }
 
 #[derive(Debug, Clone)]
@@ -34,10 +45,40 @@ pub enum FileMessage {
 
 #[derive(Debug, Clone)]
 pub enum SelectorMessage {
-    TagMessage(usize, TagMessage)
+    TagMessage(usize, TagMessage),
+    ComputedTagMessage(usize, TagMessage)
 }
 
 #[derive(Debug, Clone)]
 pub enum TagMessage {
     Selected(bool)
 }
+
+#[derive(Debug, Clone)]
+pub enum WatcherMessage {
+    Created(PathBuf),
+    Removed(PathBuf),
+    Modified(PathBuf),
+    Moved(PathBuf, PathBuf)
+}
+
+#[derive(Debug, Clone)]
+pub enum MigratorMessage {
+    MigrationMessage(usize, MigrationMessage)
+}
+
+#[derive(Debug, Clone)]
+pub enum MigrationMessage {
+    Applied,
+    Dismissed
+}
+
+#[derive(Debug, Clone)]
+pub enum ErrorPanelMessage {
+    Cleared
+}
+
+#[derive(Debug, Clone)]
+pub enum SwitcherMessage {
+    RootSelected(usize)
+}
@@ -0,0 +1,75 @@
+use ark_tagger::index::Change;
+use ark_tagger::model::tag::Tag;
+use super::message::{MigratorMessage, MigrationMessage};
+
+use iced::{Element, Row, Column, Length, Button, Text, button};
+
+pub struct Migrator {
+    widgets: Vec<MigrationWidget>
+}
+
+impl Migrator {
+    pub fn new() -> Self {
+        Migrator { widgets: vec![] }
+    }
+
+    pub fn offer(&mut self, change: Change, tags: Vec<Tag>) {
+        self.widgets.retain(|widget| widget.change.path != change.path);
+        self.widgets.push(MigrationWidget::new(change, tags));
+    }
+
+    pub fn take(&mut self, i: usize) -> Change {
+        self.widgets.remove(i).change
+    }
+
+    pub fn view(&mut self) -> Element<MigratorMessage> {
+        self.widgets
+            .iter_mut()
+            .enumerate()
+            .fold(Column::new(), |column, (i, widget)|
+                column.push(widget.view()
+                    .map(move |msg| MigratorMessage::MigrationMessage(i, msg))))
+            .into()
+    }
+}
+
+struct MigrationWidget {
+    change: Change,
+    label: String,
+    apply_button: button::State,
+    dismiss_button: button::State,
+}
+
+impl MigrationWidget {
+    fn new(change: Change, tags: Vec<Tag>) -> Self {
+        let label = format!("{:?} has been changed, move tags {} to the new content?",
+            change.path.file_name().unwrap(), tags.join(", "));
+
+        MigrationWidget {
+            change,
+            label,
+            apply_button: button::State::new(),
+            dismiss_button: button::State::new(),
+        }
+    }
+
+    fn view(&mut self) -> Element<MigrationMessage> {
+        let label = Text::new(&self.label)
+            .size(12)
+            .width(Length::Fill);
+
+        let apply =
+            Button::new(&mut self.apply_button, Text::new("move"))
+                .on_press(MigrationMessage::Applied);
+
+        let dismiss =
+            Button::new(&mut self.dismiss_button, Text::new("dismiss"))
+                .on_press(MigrationMessage::Dismissed);
+
+        Row::new()
+            .push(label)
+            .push(apply)
+            .push(dismiss)
+            .into()
+    }
+}
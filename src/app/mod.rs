@@ -4,24 +4,45 @@ mod browser;
 mod message;
 mod order;
 mod style;
+mod watcher;
+mod migrator;
+mod errors;
+mod switcher;
+mod daemon;
 
-use crate::model::{
+use ark_tagger::model::{
     Model, id::Id,
-    tag::HighlightedTag,
-    location::Location
+    tag::{Tag, HighlightedTag},
+    location::Location,
+    computed
 };
-use crate::utils;
-
-use message::{Message, TaggerMessage, BrowserMessage, FileMessage, DirMessage};
+use ark_tagger::index::{Change, MigrationPolicy};
+use ark_tagger::error::Result;
+use ark_tagger::utils;
+use ark_tagger::config::Config;
+use ark_tagger::database::Database;
+use ark_tagger::ipc::{self, Client, Event};
+use ark_tagger::xattrs;
+use crate::cli::Options;
+
+use order::Order;
+use message::{Message, TaggerMessage, BrowserMessage, FileMessage, DirMessage, WatcherMessage, MigratorMessage, MigrationMessage,
+    ErrorPanelMessage, SwitcherMessage};
 
 use tagger::Tagger;
 use selector::Selector;
 use browser::Browser;
+use migrator::Migrator;
+use errors::ErrorPanel;
+use switcher::RootSwitcher;
 
 use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::collections::BTreeSet;
+use std::process;
 
 use iced::{
-    Application, Command,
+    Application, Command, Subscription,
     Container, Element,
     Column, Length
 };
@@ -31,33 +52,42 @@ pub struct RootWidget {
     tagger: Tagger,
     selector: Selector,
     browser: Browser,
+    migrator: Migrator,
+    errors: ErrorPanel,
+    switcher: RootSwitcher,
+    //tagging goes through the daemon serving the root, if there is one
+    client: Option<Client>,
+    //command line options are applied again when the configuration is reloaded
+    options: Options,
+    config: Config,
 }
 
 impl Application for RootWidget {
     type Executor = iced::executor::Default;
     type Message = Message;
 
-    type Flags = PathBuf;
-
-    fn new(root: Self::Flags) -> (Self, Command<Message>) {
-        let mut model = Model::new(root);
-        let location = &mut model.location;
-        let index = &mut model.index;
-
-        let ids = location.files.iter()
-            .map(|e| index.id(&e.path));
-
-        let tags = model.database.sieved_tags(ids);
-        let selector = Selector::new(tags);
+    type Flags = (Options, Config);
 
-        let browser = Browser::new(
-            &location.directories,
-            location.files.iter(),
-            false);
+    fn new((options, config): Self::Flags) -> (Self, Command<Message>) {
+        let root = options.root.clone().unwrap();
+        let mut model = Model::new(root, config.migration, &config)
+            .unwrap_or_else(|error| {
+                println!("ERROR: {}", error);
+                process::exit(1)
+            });
 
+        let (selector, browser, switcher) = Self::widgets(&mut model, &config);
         let tagger = Tagger::new();
+        let migrator = Migrator::new();
+        let errors = ErrorPanel::new();
+
+        let client = Self::connect(&model);
+        let mut root = RootWidget { model, tagger, selector, browser, migrator, errors, switcher, client, options, config };
+        root.migrate_tags();
+        root.save_index();
+        root.collect_errors();
 
-        (RootWidget { model, tagger, selector, browser }, Command::none())
+        (root, Command::none())
     }
 
     fn title(&self) -> String {
@@ -65,36 +95,57 @@ impl Application for RootWidget {
     }
 
     fn update(&mut self, msg: Message) -> Command<Message> {
-        println!("Application::update(): {:?}", &msg);
+        ark_tagger::trace!("Application::update(): {:?}", &msg);
         match msg {
+            //nothing to tag with, the selection is kept
+            Message::TaggerMessage(TaggerMessage::TaggingActivated) if self.tagger.text.is_empty() => {},
             Message::TaggerMessage(TaggerMessage::TaggingActivated) => {
                 let files_selection = self.browser.take_model_selection();
                 let tag = self.tagger.take_tag();
 
-                println!("\tTagging {:?} with {:?}", files_selection, tag);
+                ark_tagger::trace!("\tTagging {:?} with {:?}", files_selection, tag);
 
                 let file_entries = &self.model.location.files;
                 let file_paths: Vec<PathBuf> = file_entries.iter()
                     .map(|e| e.path.clone())
                     .collect();
 
+                if let Some(client) = &mut self.client {
+                    let paths = files_selection.into_iter()
+                        .map(|i| file_paths[i].clone())
+                        .collect();
+
+                    //the database is reloaded when the daemon reports the change
+                    if let Err(error) = client.tag(paths, &tag) {
+                        self.errors.push(error);
+                    }
+                    return Command::none();
+                }
+
                 let index = &mut self.model.index;
                 let database = &mut self.model.database;
 
                 let ids = files_selection.into_iter()
-                    .map(|i| {
+                    .filter_map(|i| {
                         let path = &file_paths[i];
-                        println!("\t\t{:?}", &path);
+                        ark_tagger::trace!("\t\t{:?}", &path);
 
                         index.id(path)
                     });
 
-                if database.insert(ids, &tag) {
-                    //if we just tagged some file then the tag's bucket is not empty
-                    self.selector.insert(HighlightedTag {
-                        highlighted: true,
-                        tag: &tag
-                    });
+                match database.insert(ids, &tag) {
+                    Ok(true) => {
+                        //if we just tagged some file then the tag's bucket is not empty
+                        self.selector.insert(HighlightedTag {
+                            highlighted: true,
+                            tag: &tag
+                        });
+                    },
+                    Ok(false) => {},
+                    Err(error) => self.errors.push(error)
+                }
+                for error in xattrs::write_through(&mut self.model) {
+                    self.errors.push(error);
                 }
 
                 //todo: update only sieve
@@ -108,40 +159,131 @@ impl Application for RootWidget {
                 self.update_filter_and_sieve();
             },
             Message::BrowserMessage(BrowserMessage::AscendActivated) => {
-                println!("\tAscending");
+                ark_tagger::trace!("\tAscending");
                 let location = self.model.location.ascend(&mut self.model.index);
                 self.change_location(location);
             },
             Message::BrowserMessage(BrowserMessage::DirMessage(i, DirMessage::DescendActivated)) => {
-                println!("\tDescending into {}th entry", i);
+                ark_tagger::trace!("\tDescending into {}th entry", i);
                 let location = self.model.location.descend(&mut self.model.index, i);
                 self.change_location(location);
             }
             Message::BrowserMessage(BrowserMessage::FileMessage(i, FileMessage::ExecuteActivated)) => {
-                println!("\tActivating {}th file", i);
-                self.model.location.activate(i);
+                ark_tagger::trace!("\tActivating {}th file", i);
+                if let Err(error) = self.model.location.activate(i) {
+                    self.errors.push(error);
+                }
             },
             Message::BrowserMessage(BrowserMessage::OrderSelected(order)) => {
                 self.browser.update(BrowserMessage::OrderSelected(order));
                 self.update_filter_and_sieve(); //todo: this is a hack to cause refresh() with proper entries
             },
+            Message::BrowserMessage(BrowserMessage::HiddenToggled(show_hidden)) => {
+                ark_tagger::trace!("\tShowing hidden files: {}", show_hidden);
+                let location = self.model.location.reload(&mut self.model.index, show_hidden);
+                self.change_location(location);
+            },
             Message::BrowserMessage(msg) => {
                 self.browser.update(msg)
             },
+            Message::WatcherMessage(msg) if Self::affects_ignores(&msg, &self.model.names().ignore) => {
+                ark_tagger::trace!("\tIgnore rules have been changed");
+                let show_hidden = self.model.location.show_hidden();
+                let location = self.model.location.reload(&mut self.model.index, show_hidden);
+                self.change_location(location);
+            },
+            Message::WatcherMessage(msg) => {
+                let location = &mut self.model.location;
+                let index = &mut self.model.index;
+
+                let changed = match &msg {
+                    WatcherMessage::Created(path) => location.created(index, path),
+                    WatcherMessage::Removed(path) => location.removed(index, path),
+                    WatcherMessage::Modified(path) => location.modified(index, path),
+                    WatcherMessage::Moved(from, to) => location.moved(index, from, to),
+                };
+
+                if changed {
+                    self.browser.refresh_directories(&self.model.location.directories);
+                    self.migrate_tags();
+                    self.update_filter_and_sieve();
+                    self.save_index();
+                }
+                self.collect_errors();
+            },
+            Message::MigratorMessage(MigratorMessage::MigrationMessage(i, MigrationMessage::Applied)) => {
+                let change = self.migrator.take(i);
+                if let Err(error) = self.apply_migration(change) {
+                    self.errors.push(error);
+                }
+                self.update_filter_and_sieve();
+            },
+            Message::MigratorMessage(MigratorMessage::MigrationMessage(i, MigrationMessage::Dismissed)) => {
+                self.migrator.take(i);
+            },
+            Message::ErrorPanelMessage(msg) => {
+                self.errors.update(msg);
+            },
+            Message::SwitcherMessage(SwitcherMessage::RootSelected(i)) => {
+                let registration = self.switcher.registration(i);
+                ark_tagger::trace!("\tSwitching to {:?}", registration.root);
+                self.switch_root(registration.root, registration.database);
+            },
+            Message::DaemonMessage(event) => {
+                self.reload_database();
+
+                match event {
+                    Event::Tagged { tag, .. } if !self.selector.contains(&tag) =>
+                        self.selector.insert(HighlightedTag { highlighted: true, tag: &tag }),
+                    Event::Untagged { tag, .. } if !self.model.database.contains(&tag) =>
+                        self.selector.remove(&tag),
+                    _ => {}
+                }
+
+                self.update_filter_and_sieve();
+                self.collect_errors();
+            },
+            Message::ConfigMessage(msg) => {
+                let path = Config::path();
+                if Self::paths(&msg).into_iter().any(|changed| Some(changed) == path.as_ref()) {
+                    self.reload_config();
+                }
+            },
         }
 
         Command::none()
     }
 
+    fn subscription(&self) -> Subscription<Message> {
+        let location = watcher::watch(self.model.location.path().to_path_buf())
+            .map(Message::WatcherMessage);
+
+        let config = Config::path()
+            .and_then(|path| path.parent().map(|dir| dir.to_path_buf()))
+            .map(|dir| watcher::watch(dir).map(Message::ConfigMessage));
+
+        let daemon = self.client.as_ref()
+            .map(|_| daemon::events(ipc::socket_path(&self.model.root))
+                .map(Message::DaemonMessage));
+
+        Subscription::batch(vec![Some(location), config, daemon].into_iter().flatten())
+    }
+
     fn view(&mut self) -> Element<Message> {
         let root: Element<Message> = Container::new::<Element<Message>>(
                 Column::new()
+                    .push(self.switcher.view()
+                        .map(|msg| { Message::SwitcherMessage(msg) }))
                     .push(self.browser.view() //todo: scrolling doesn't look working
                         .map(|msg| { Message::BrowserMessage(msg) }))
                     .push(self.selector.view()
                         .map(|msg| { Message::SelectorMessage(msg) }))
                     .push(self.tagger.view()
                         .map(|msg| { Message::TaggerMessage(msg) }))
+                    .push(self.migrator.view()
+                        .map(|msg| { Message::MigratorMessage(msg) }))
+                    .push(self.errors.view()
+                        .map(|msg| { Message::ErrorPanelMessage(msg) }))
                     .align_items(iced::Align::Center)
                     .into())
             .width(Length::Fill)
@@ -160,14 +302,184 @@ impl Application for RootWidget {
 }
 
 impl RootWidget {
-    fn change_location(&mut self, location: Location) {
+    fn widgets(model: &mut Model, config: &Config) -> (Selector, Browser, RootSwitcher) {
+        let location = &mut model.location;
+        let index = &mut model.index;
+
+        let ids = location.files.iter()
+            .filter_map(|e| index.id(&e.path));
+
+        let tags = model.database.sieved_tags(ids);
+        let selector = Selector::new(tags);
+
+        let browser = Browser::new(
+            &location.directories,
+            location.files.iter(),
+            false,
+            location.show_hidden())
+            .with_order(Self::order(config));
+
+        let switcher = RootSwitcher::new(&model.root, model.database.path(), config);
+
+        (selector, browser, switcher)
+    }
+
+    //the index of the previous root is saved already,
+    //pending migrations and selection belong to the previous root
+    fn switch_root(&mut self, root: PathBuf, database: Option<PathBuf>) {
+        let config = Config { database, ..self.config.clone() };
+        let model = Model::new(root, config.migration, &config);
+        let mut model = match model {
+            Ok(model) => model,
+            Err(error) => {
+                self.errors.push(error);
+                return;
+            }
+        };
+
+        let (selector, browser, switcher) = Self::widgets(&mut model, &config);
+        self.client = Self::connect(&model);
+        self.model = model;
+        self.selector = selector;
+        self.browser = browser;
+        self.switcher = switcher;
+        self.migrator = Migrator::new();
+
+        self.migrate_tags();
+        self.update_filter_and_sieve();
+        self.save_index();
+        self.collect_errors();
+    }
+
+    fn change_location(&mut self, location: Result<Location>) {
+        let location = match location {
+            Ok(location) => location,
+            Err(error) => {
+                self.errors.push(error);
+                return;
+            }
+        };
+
         let files = vec![];
         let files = files.iter();
         //todo: remove this hack
 
-        self.browser = Browser::new(&location.directories, files,location.depth > 0);
+        self.browser = Browser::new(&location.directories, files,location.depth > 0, location.show_hidden())
+            .with_order(Self::order(&self.config));
         self.model.location = location;
+        self.migrate_tags();
         self.update_filter_and_sieve();
+        self.save_index();
+        self.collect_errors();
+    }
+
+    //the model is rebuilt at the root, since ignore patterns,
+    //hashing mode or the database could change
+    fn reload_config(&mut self) {
+        let mut config = match Config::load() {
+            Ok(config) => config,
+            Err(error) => {
+                self.errors.push(error);
+                return;
+            }
+        };
+        self.options.apply(&mut config);
+        //names of the service files are kept until restart
+        config.names = self.config.names.clone();
+        ark_tagger::trace!("\tConfiguration has been reloaded");
+
+        self.config = config;
+        let root = self.model.root.clone();
+        let database = self.config.database.clone();
+        self.switch_root(root, database);
+    }
+
+    fn connect(model: &Model) -> Option<Client> {
+        let client = Client::connect(&ipc::socket_path(&model.root)).ok();
+        if client.is_some() {
+            println!("Connected to the daemon serving {:?}", model.root);
+        }
+        client
+    }
+
+    //the daemon writes the database, so our copy gets outdated
+    fn reload_database(&mut self) {
+        let path = self.model.database.path().to_path_buf();
+        match Database::new(path) {
+            Ok(database) => self.model.database = database,
+            Err(error) => self.errors.push(error)
+        }
+    }
+
+    fn order(config: &Config) -> Order {
+        config.order.as_ref()
+            .and_then(|order| order.parse()
+                .map_err(|error| println!("WARNING: {}", error))
+                .ok())
+            .unwrap_or(Order::AsIs)
+    }
+
+    fn save_index(&mut self) {
+        if let Err(error) = self.model.index.save() {
+            self.errors.push(error);
+        }
+    }
+
+    fn affects_ignores(msg: &WatcherMessage, name: &str) -> bool {
+        Self::paths(msg).into_iter()
+            .any(|path| path.file_name() == Some(OsStr::new(name)))
+    }
+
+    fn paths(msg: &WatcherMessage) -> Vec<&PathBuf> {
+        match msg {
+            WatcherMessage::Created(path) |
+            WatcherMessage::Removed(path) |
+            WatcherMessage::Modified(path) => vec![path],
+            WatcherMessage::Moved(from, to) => vec![from, to]
+        }
+    }
+
+    fn collect_errors(&mut self) {
+        let location_errors = self.model.location.take_errors();
+        let database_errors = self.model.database.take_errors();
+
+        for error in location_errors.into_iter().chain(database_errors) {
+            self.errors.push(error);
+        }
+    }
+
+    //tags of files with changed content are moved according to the policy
+    fn migrate_tags(&mut self) {
+        for error in self.model.migrate_rehashed() {
+            self.errors.push(error);
+        }
+
+        for change in self.model.index.take_changes() {
+            let tags: Vec<_> = self.model.database.tags_of(change.old)
+                .cloned()
+                .collect();
+            if tags.is_empty() {
+                continue;
+            }
+
+            match self.model.policy() {
+                MigrationPolicy::Apply => {
+                    if let Err(error) = self.apply_migration(change) {
+                        self.errors.push(error);
+                    }
+                },
+                MigrationPolicy::Ask => self.migrator.offer(change, tags),
+                MigrationPolicy::Ignore => {}
+            }
+        }
+    }
+
+    //todo: migrations should go through the daemon too
+    fn apply_migration(&mut self, change: Change) -> Result<()> {
+        //copies with the old content may be outside of visited locations
+        let tags = self.model.database.migrate(change.old, change.new, true)?;
+        ark_tagger::trace!("\tMoved tags {:?} of {:?}", tags, change.path);
+        Ok(())
     }
 
     //todo: highlight directories with matching files
@@ -179,15 +491,31 @@ impl RootWidget {
         //todo: ids provision should be in Location
         //todo: ids and files in location must be synced
         let files = &location.files;
-        let ids: Vec<Id> = files.iter()
+        let ids: Vec<Option<Id>> = files.iter()
             .map(|entry| index.id(entry.path.as_path()))
             .collect();
 
-        let filter = self.model.database.filter(ids.iter().copied(), self.selector.selection());
+        let computed: Vec<BTreeSet<Tag>> = files.iter()
+            .map(computed::of)
+            .collect();
+        let selection: Vec<Tag> = self.selector.selection()
+            .chain(self.selector.computed_selection())
+            .cloned()
+            .collect();
+
+        let filter = computed::filter(&self.model.database, &ids, &computed, selection.iter());
         let filtered_ids = utils::apply_filter(ids.iter(), filter.iter().copied());
-        let sieve = self.model.database.sieve(filtered_ids.copied());
+        let sieve = self.model.database.sieve(filtered_ids.flatten().copied());
         self.selector.highlight(sieve);
 
+        //computed tags of all files here, highlighted if filtered files have them
+        let highlighted: BTreeSet<&Tag> = utils::apply_filter(computed.iter(), filter.iter().copied())
+            .flatten()
+            .collect();
+        let all: BTreeSet<&Tag> = computed.iter().flatten().collect();
+        self.selector.refresh_computed(all.into_iter()
+            .map(|tag| HighlightedTag { highlighted: highlighted.contains(tag), tag }));
+
         let filtered_files = utils::apply_filter(files.iter(), filter.into_iter());
         self.browser.refresh(filtered_files);
     }
@@ -9,6 +9,8 @@ pub enum Order {
 
 use Order::*;
 
+use std::str::FromStr;
+
 impl Order {
     pub fn all() -> [(Order, &'static str); 5] {
         [(AsIs, "no order"),
@@ -17,4 +19,19 @@ impl Order {
             (ByModifiedDate, "by modification"),
             (ByAccessedDate, "by last access")]
     }
-}
\ No newline at end of file
+}
+//names used in the configuration and in the command line
+impl FromStr for Order {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(AsIs),
+            "size" => Ok(BySize),
+            "created" => Ok(ByCreatedDate),
+            "modified" => Ok(ByModifiedDate),
+            "accessed" => Ok(ByAccessedDate),
+            _ => Err(format!("unknown order: {}", s))
+        }
+    }
+}
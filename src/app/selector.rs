@@ -1,16 +1,18 @@
-use crate::model::tag::{Tag, HighlightedTag};
+use ark_tagger::model::tag::{Tag, HighlightedTag};
 
 use super::message::{SelectorMessage, TagMessage};
 use super::style::TagStyle;
 
-use iced::{Element, Color, Checkbox, Row};
+use iced::{Element, Color, Checkbox, Row, Column};
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
 pub struct Selector {
     tag_widgets: Vec<TagWidget>,
     selection: Vec<bool>,
-    hasher: Box<dyn Hasher>
+    hasher: Box<dyn Hasher>,
+    //tags computed for files of the current location, shown in a row of their own
+    computed_widgets: Vec<TagWidget>,
 }
 
 impl Selector {
@@ -26,7 +28,8 @@ impl Selector {
         Selector {
             tag_widgets: widgets,
             selection: vec![false; n],
-            hasher: Box::new(hasher)
+            hasher: Box::new(hasher),
+            computed_widgets: vec![]
         }
     }
 
@@ -45,19 +48,50 @@ impl Selector {
         self.selection.insert(pos, false);
     }
 
+    pub fn contains(&self, tag: &Tag) -> bool {
+        self.tag_widgets.iter().any(|widget| widget.tag == *tag)
+    }
+
+    pub fn remove(&mut self, tag: &Tag) {
+        if let Some(pos) = self.tag_widgets.iter().position(|widget| widget.tag == *tag) {
+            self.tag_widgets.remove(pos);
+            self.selection.remove(pos);
+        }
+    }
+
+    //computed tags which are still there stay selected
+    pub fn refresh_computed<'a, T>(&mut self, tags: T)
+    where T: Iterator<Item = HighlightedTag<'a>> {
+        let selected: Vec<Tag> = self.computed_selection().cloned().collect();
+
+        self.computed_widgets = tags
+            .map(|tag| {
+                //colors don't depend on other computed tags
+                let mut widget = TagWidget::new(tag, &mut DefaultHasher::new());
+                widget.selected = selected.contains(&widget.tag);
+                widget
+            })
+            .collect();
+    }
+
     pub fn update(&mut self, msg: SelectorMessage) {
-        println!("\tSelectorMessage: {:?}", &msg);
+        ark_tagger::trace!("\tSelectorMessage: {:?}", &msg);
         match msg {
             SelectorMessage::TagMessage(i, msg) => {
                 match &msg {
                     TagMessage::Selected(value) => { self.selection[i] = *value; },
                 };
-                println!("[ Tags selected: {:?} ]", &self.selection);
+                ark_tagger::trace!("[ Tags selected: {:?} ]", &self.selection);
 
                 if let Some(tag) = self.tag_widgets.get_mut(i) {
                     tag.update(msg);
                 }
             },
+            SelectorMessage::ComputedTagMessage(i, msg) => {
+                if let Some(tag) = self.computed_widgets.get_mut(i) {
+                    tag.update(msg);
+                }
+            },
         }
     }
 
@@ -74,15 +108,26 @@ impl Selector {
                 .map(|(i, _)| i)
                 .collect::<Vec<usize>>());
 
-        self.tag_widgets
+        let stored = self.tag_widgets
             .iter_mut()
             .enumerate()
             .fold(Row::new(), |row, (i, tag)|
                 row.push(tag.view()
                     .map(move |msg| {
-                        println!("Selector::view(): a message from Tag: {:?}", &msg);
+                        ark_tagger::trace!("Selector::view(): a message from Tag: {:?}", &msg);
                         SelectorMessage::TagMessage(i, msg)
-                    })))
+                    })));
+
+        let computed = self.computed_widgets
+            .iter_mut()
+            .enumerate()
+            .fold(Row::new(), |row, (i, tag)|
+                row.push(tag.view()
+                    .map(move |msg| SelectorMessage::ComputedTagMessage(i, msg))));
+
+        Column::new()
+            .push(stored)
+            .push(computed)
             .into()
     }
 
@@ -101,6 +146,12 @@ impl Selector {
             .filter(|(i, _)| **i)
             .map(|(_, widget)| &widget.tag)
     }
+
+    pub fn computed_selection(&self) -> impl Iterator<Item = &Tag> {
+        self.computed_widgets.iter()
+            .filter(|widget| widget.selected)
+            .map(|widget| &widget.tag)
+    }
 }
 
 pub struct TagWidget {
@@ -0,0 +1,69 @@
+use ark_tagger::volume::{self, Registry, Registration};
+use ark_tagger::config::Config;
+use super::message::SwitcherMessage;
+
+use iced::{Element, Row, Length, Button, Text, button};
+
+use std::path::Path;
+
+//roots registered with the same database and mounted at the moment,
+//and roots listed in the configuration
+pub struct RootSwitcher {
+    roots: Vec<RootButton>
+}
+
+struct RootButton {
+    registration: Registration,
+    label: String,
+    state: button::State
+}
+
+impl RootSwitcher {
+    pub fn new(current: &Path, database: &Path, config: &Config) -> Self {
+        let registry = Registry::load()
+            .map_err(|error| println!("WARNING: {}", error))
+            .ok();
+
+        let shared: Vec<Registration> = registry.iter()
+            .flat_map(|registry| registry.sharing(database))
+            .filter(|(id, registration)| volume::mounted(id, registration, &config.names.volume))
+            .map(|(_, registration)| registration.clone())
+            .collect();
+
+        let configured = config.roots.iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .map(|root| Registration { root, database: config.database.clone() });
+
+        let mut registrations: Vec<Registration> = vec![];
+        for registration in shared.into_iter().chain(configured) {
+            if registration.root != current &&
+                registrations.iter().all(|known| known.root != registration.root) {
+                registrations.push(registration);
+            }
+        }
+
+        let roots = registrations.into_iter()
+            .map(|registration| RootButton {
+                label: registration.root.to_string_lossy().into_owned(),
+                registration,
+                state: button::State::new()
+            })
+            .collect();
+
+        RootSwitcher { roots }
+    }
+
+    pub fn registration(&self, i: usize) -> Registration {
+        self.roots[i].registration.clone()
+    }
+
+    pub fn view(&mut self) -> Element<SwitcherMessage> {
+        self.roots
+            .iter_mut()
+            .enumerate()
+            .fold(Row::new().width(Length::Fill), |row, (i, root)|
+                row.push(Button::new(&mut root.state, Text::new(&root.label).size(12))
+                    .on_press(SwitcherMessage::RootSelected(i))))
+            .into()
+    }
+}
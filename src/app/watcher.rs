@@ -0,0 +1,155 @@
+use super::message::WatcherMessage;
+
+use iced::Subscription;
+use iced_native::subscription::Recipe;
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::stream::{BoxStream, StreamExt};
+use inotify::{Inotify, WatchMask, EventMask};
+
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::any::TypeId;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::Arc;
+use std::thread;
+use std::io;
+
+pub fn watch(path: PathBuf) -> Subscription<WatcherMessage> {
+    Subscription::from_recipe(Watcher { path })
+}
+
+pub struct Watcher {
+    path: PathBuf
+}
+
+impl<H, I> Recipe<H, I> for Watcher
+where H: Hasher {
+    type Output = WatcherMessage;
+
+    fn hash(&self, state: &mut H) {
+        TypeId::of::<Self>().hash(state);
+        self.path.hash(state);
+    }
+
+    fn stream(self: Box<Self>, _input: BoxStream<'static, I>) -> BoxStream<'static, Self::Output> {
+        let (sender, receiver) = mpsc::unbounded();
+
+        let path = self.path;
+        let shutdown = match EventFd::new() {
+            Ok(shutdown) => Arc::new(shutdown),
+            Err(error) => {
+                println!("WARNING: can't watch {:?}: {}", path, error);
+                return Box::pin(receiver);
+            }
+        };
+
+        let listener = shutdown.clone();
+        thread::spawn(move || {
+            if let Err(error) = listen(&path, &listener, sender) {
+                println!("WARNING: can't watch {:?}: {}", path, error);
+            }
+        });
+
+        //the stream owns the guard, so dropping the subscription stops the thread
+        let guard = Shutdown(shutdown);
+        Box::pin(receiver.map(move |message| {
+            let _ = &guard;
+            message
+        }))
+    }
+}
+
+struct EventFd(RawFd);
+
+impl EventFd {
+    fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(EventFd(fd))
+    }
+
+    fn notify(&self) {
+        unsafe { libc::eventfd_write(self.0, 1); }
+    }
+}
+
+impl Drop for EventFd {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.0); }
+    }
+}
+
+struct Shutdown(Arc<EventFd>);
+
+impl Drop for Shutdown {
+    fn drop(&mut self) {
+        self.0.notify();
+    }
+}
+
+fn listen(path: &Path, shutdown: &EventFd, sender: UnboundedSender<WatcherMessage>) -> io::Result<()> {
+    let mut inotify = Inotify::init()?;
+    inotify.add_watch(path,
+        WatchMask::CREATE | WatchMask::DELETE | WatchMask::CLOSE_WRITE |
+        WatchMask::MOVED_FROM | WatchMask::MOVED_TO)?;
+
+    let mut buffer = [0; 4096];
+    loop {
+        let mut fds = [
+            libc::pollfd { fd: inotify.as_raw_fd(), events: libc::POLLIN, revents: 0 },
+            libc::pollfd { fd: shutdown.0, events: libc::POLLIN, revents: 0 },
+        ];
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) } < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(error);
+        }
+        if fds[1].revents != 0 {
+            return Ok(());
+        }
+
+        let events = inotify.read_events(&mut buffer)?;
+
+        //renaming is reported as a pair of events with the same cookie
+        let mut moved_from: HashMap<u32, PathBuf> = HashMap::new();
+        let mut messages = vec![];
+
+        for event in events {
+            let name = match event.name {
+                Some(name) => name,
+                None => continue
+            };
+            let target = path.join(name);
+
+            if event.mask.contains(EventMask::MOVED_FROM) {
+                moved_from.insert(event.cookie, target);
+            } else if event.mask.contains(EventMask::MOVED_TO) {
+                messages.push(match moved_from.remove(&event.cookie) {
+                    Some(source) => WatcherMessage::Moved(source, target),
+                    None => WatcherMessage::Created(target)
+                });
+            } else if event.mask.contains(EventMask::CREATE) {
+                messages.push(WatcherMessage::Created(target));
+            } else if event.mask.contains(EventMask::DELETE) {
+                messages.push(WatcherMessage::Removed(target));
+            } else if event.mask.contains(EventMask::CLOSE_WRITE) {
+                messages.push(WatcherMessage::Modified(target));
+            }
+        }
+
+        //moved outside of the watched directory
+        messages.extend(moved_from.into_values()
+            .map(WatcherMessage::Removed));
+
+        for message in messages {
+            if sender.unbounded_send(message).is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use ark_tagger::model::entry::SymlinkPolicy;
+use ark_tagger::index::{Hashing, MigrationPolicy};
+use ark_tagger::config::Config;
+
+use structopt::StructOpt;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default, StructOpt)]
+#[structopt(name = "ark-tagger")]
+pub struct Options {
+    /// Root of the tagged tree, the current directory by default
+    #[structopt(parse(from_os_str))]
+    pub root: Option<PathBuf>,
+
+    /// What to do with symbolic links: follow, skip or link (tag the link itself),
+    /// `follow` unless set in the configuration file
+    #[structopt(long)]
+    pub symlinks: Option<SymlinkPolicy>,
+
+    /// Re-hash files every time (always) or only when changed (cached)
+    #[structopt(long)]
+    pub hashing: Option<Hashing>,
+
+    /// What to do with tags of files which content has changed:
+    /// apply, ask or ignore; `ask` unless set in the configuration file
+    #[structopt(long)]
+    pub migration: Option<MigrationPolicy>,
+
+    /// Initial order of files: none, size, created, modified or accessed
+    #[structopt(long)]
+    pub order: Option<String>,
+
+    /// Central database shared by several roots, `.ark-tags.data` in the root by default
+    #[structopt(long, parse(from_os_str))]
+    pub database: Option<PathBuf>,
+
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Without a command, the graphical interface is started
+#[derive(Debug, Clone, StructOpt)]
+pub enum Command {
+    /// Check integrity of the database
+    Fsck {
+        /// Remove malformed entries and empty buckets, rename legacy buckets
+        #[structopt(long)]
+        repair: bool,
+
+        /// Remove ids which no indexed file has,
+        /// refused unless the whole tree has been scanned
+        #[structopt(long)]
+        prune: bool,
+    },
+
+    /// List ids which are tagged but have no files anymore
+    Orphans,
+
+    /// Move tags of files which don't exist anymore into the archive,
+    /// nothing is collected unless the whole tree has been scanned
+    Gc {
+        /// Keep orphans noticed less than this number of days ago
+        #[structopt(long, default_value = "30")]
+        grace: u64,
+
+        /// Remove orphans for good instead of archiving them
+        #[structopt(long)]
+        force: bool,
+    },
+
+    /// List collections known on this machine with their last mount points
+    Volumes,
+
+    /// Re-hash indexed files to find content changed without modification time change
+    Verify {
+        /// Report only corrupted files and errors, e.g. when running from cron
+        #[structopt(long)]
+        quiet: bool,
+    },
+
+    /// Serve tagging requests over a Unix socket, the graphical interface
+    /// and other tools become its clients
+    Daemon {
+        /// Socket to listen on, derived from the root by default
+        #[structopt(long, parse(from_os_str))]
+        socket: Option<PathBuf>,
+    },
+
+    /// Maintain a directory of symbolic links by tag, e.g. `cats/` and `cats+dogs/`
+    Farm {
+        /// Directory outside of the root, created if missing
+        #[structopt(parse(from_os_str))]
+        path: PathBuf,
+
+        /// Maximal number of tags combined in one directory
+        #[structopt(long, default_value = "2")]
+        depth: usize,
+
+        /// Keep updating the links while the daemon serving the root reports changes
+        #[structopt(long)]
+        follow: bool,
+    },
+
+    /// Serve a JSON API over HTTP on localhost
+    Http {
+        #[structopt(long, default_value = "7878")]
+        port: u16,
+
+        /// Token expected in `Authorization: Bearer <token>`, generated if not given
+        #[structopt(long)]
+        token: Option<String>,
+
+        /// Reject tagging and untagging
+        #[structopt(long)]
+        read_only: bool,
+    },
+
+    /// Sync tags with the `user.xdg.tags` attribute of files both ways
+    Xattrs {
+        /// Only import tags, leave the attributes untouched
+        #[structopt(long)]
+        import_only: bool,
+    },
+
+    /// Write tags of tagged files into XMP sidecars, e.g. `photo.jpg.xmp`,
+    /// existing sidecars are merged with
+    Sidecars,
+
+    /// Import tags from metadata of indexed files or from other tag databases,
+    /// only previewed without `--apply`
+    Import {
+        /// Tag the files instead of listing the proposed tags
+        #[structopt(long)]
+        apply: bool,
+
+        #[structopt(subcommand)]
+        source: Source,
+    },
+
+    /// Mount tags as directories, e.g. `cats/dogs/` lists files having both tags
+    #[cfg(feature = "filesystem")]
+    Mount {
+        /// Empty directory to mount on
+        #[structopt(parse(from_os_str))]
+        mountpoint: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, StructOpt)]
+pub enum Source {
+    /// Keywords of photos from IPTC and XMP
+    Photos {
+        /// Also derive `year/...`, `camera/...` and `geotagged` from EXIF
+        #[structopt(long)]
+        derived: bool,
+    },
+
+    /// Artist, album, genre and year of MP3, FLAC and Ogg files
+    /// as `artist:...`, `album:...`, `genre:...` and `year:...`
+    Music,
+
+    /// Subjects of XMP sidecars, e.g. `photo.jpg.xmp`
+    Sidecars,
+
+    /// Tags of a TMSU database, tags with values as `name=value`
+    Tmsu {
+        /// Database file, e.g. `.tmsu/db`
+        #[structopt(parse(from_os_str))]
+        database: PathBuf,
+    },
+
+    /// Rows of `path,tag1;tag2`, paths relative to the root or absolute
+    Csv {
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+}
+
+impl Options {
+    //flags given in the command line take precedence over the configuration
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(symlinks) = self.symlinks {
+            config.symlinks = symlinks;
+        }
+        if let Some(hashing) = self.hashing {
+            config.hashing = hashing;
+        }
+        if let Some(migration) = self.migration {
+            config.migration = migration;
+        }
+        if self.database.is_some() {
+            config.database = self.database.clone();
+        }
+        if self.order.is_some() {
+            config.order = self.order.clone();
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use ark_tagger::model::Model;
+use ark_tagger::ipc;
+use ark_tagger::error::Result;
+
+use std::path::PathBuf;
+
+//other tools and the graphical interface talk to the daemon
+//instead of writing the database themselves
+pub fn run(mut model: Model, socket: Option<PathBuf>) -> Result<bool> {
+    super::scan(&mut model);
+    super::import_xattrs(&mut model);
+
+    let socket = socket.unwrap_or_else(|| ipc::socket_path(&model.root));
+    println!("Listening on {:?}", socket);
+
+    ipc::serve(model, &socket)?;
+    Ok(true)
+}
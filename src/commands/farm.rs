@@ -0,0 +1,37 @@
+use ark_tagger::model::Model;
+use ark_tagger::database::Database;
+use ark_tagger::farm::Farm;
+use ark_tagger::ipc::{self, Client};
+use ark_tagger::error::Result;
+
+use std::path::PathBuf;
+
+pub fn run(mut model: Model, path: PathBuf, depth: usize, follow: bool) -> Result<bool> {
+    super::scan(&mut model);
+    super::import_xattrs(&mut model);
+
+    let farm = Farm::open(path, &model.root)?;
+    sync(&farm, &model, depth)?;
+
+    if !follow {
+        return Ok(true);
+    }
+
+    //the daemon writes the database, new files could be tagged too
+    let events = Client::connect(&ipc::socket_path(&model.root))?.subscribe()?;
+    for event in events {
+        println!("{:?}", event?);
+        model.database = Database::new(model.database.path().to_path_buf())?;
+        super::scan(&mut model);
+        super::import_xattrs(&mut model);
+        sync(&farm, &model, depth)?;
+    }
+    Ok(true)
+}
+
+fn sync(farm: &Farm, model: &Model, depth: usize) -> Result<()> {
+    let sync = farm.sync(model, depth)?;
+    println!("{:?}: {} links created, {} removed",
+        farm.path(), sync.created, sync.removed);
+    Ok(())
+}
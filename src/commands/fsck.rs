@@ -0,0 +1,49 @@
+use ark_tagger::model::Model;
+use ark_tagger::database::fsck;
+use ark_tagger::error::Result;
+
+pub fn run(model: &mut Model, repair: bool, prune: bool) -> Result<bool> {
+    //unknown ids are removed only if all files are known
+    if prune {
+        super::scan_completely(model)?;
+    } else {
+        super::scan(model);
+    }
+
+    let known = model.known_ids()?;
+    let report = fsck::check(model.database.path(), &known, repair, prune)?;
+
+    println!("Malformed entries: {}", report.malformed.len());
+    for error in report.malformed.iter() {
+        println!("\t{}", error);
+    }
+
+    println!("Legacy buckets: {}", report.legacy.len());
+    for path in report.legacy.iter() {
+        println!("\t{:?}", path);
+    }
+
+    println!("Legacy buckets holding tags of other buckets: {}", report.collisions.len());
+    for path in report.collisions.iter() {
+        println!("\t{:?}", path);
+    }
+
+    println!("Empty buckets: {}", report.empty.len());
+    for tag in report.empty.iter() {
+        println!("\t{}", tag);
+    }
+
+    println!("Ids without files: {}", report.unknown.len());
+    for (tag, id) in report.unknown.iter() {
+        println!("\t{}: {}", tag, id.to_string());
+    }
+
+    if !report.changes.is_empty() {
+        println!("Changes: {}", report.changes.len());
+        for change in report.changes.iter() {
+            println!("\t{}", change);
+        }
+    }
+
+    Ok(report.is_clean())
+}
@@ -0,0 +1,85 @@
+use ark_tagger::model::Model;
+use ark_tagger::model::id::Id;
+use ark_tagger::model::tag::Tag;
+use ark_tagger::database::Database;
+use ark_tagger::database::orphans::Orphans;
+use ark_tagger::error::Result;
+
+use chrono::{DateTime, Utc};
+
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const DAY: u64 = 24 * 60 * 60;
+
+pub fn report(model: &mut Model) -> Result<bool> {
+    super::scan(model);
+    let (orphans, since) = orphans(model)?;
+
+    println!("Ids without files: {}", orphans.len());
+    for (id, tags) in orphans.iter() {
+        let noticed = UNIX_EPOCH + Duration::from_secs(since.since(*id).unwrap());
+        let noticed: DateTime<Utc> = noticed.into();
+
+        println!("\t{} (missing since {}): {}",
+            id.to_string(), noticed.format("%d/%m/%Y"), tags.join(", "));
+    }
+
+    Ok(orphans.is_empty())
+}
+
+pub fn run(model: &mut Model, grace: u64, force: bool) -> Result<bool> {
+    super::scan_completely(model)?;
+    let (orphans, mut since) = orphans(model)?;
+    let now = now();
+
+    let mut archive = if force {
+        None
+    } else {
+        Some(Database::new(model.root.join(&model.names().archive))?)
+    };
+
+    let mut collected = 0;
+    for (id, tags) in orphans.iter() {
+        let noticed = since.since(*id).unwrap();
+        if now < noticed + grace * DAY {
+            continue;
+        }
+
+        if let Some(archive) = archive.as_mut() {
+            for tag in tags.iter() {
+                archive.insert(std::iter::once(*id), tag)?;
+            }
+        }
+
+        model.database.forget(*id)?;
+        since.forget(*id);
+        collected += 1;
+
+        println!("\t{}: {}", id.to_string(), tags.join(", "));
+    }
+
+    since.save()?;
+    println!("Collected {} of {} orphans", collected, orphans.len());
+
+    Ok(true)
+}
+
+//also records when the orphans were noticed first, the tree must be scanned
+fn orphans(model: &mut Model) -> Result<(BTreeMap<Id, Vec<Tag>>, Orphans)> {
+    let known = model.known_ids()?;
+    let orphans = model.database.orphans(&known);
+
+    let mut since = Orphans::load(model.root.join(&model.names().orphans))?;
+    since.update(orphans.keys().cloned(), now());
+    since.save()?;
+
+    Ok((orphans, since))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
@@ -0,0 +1,24 @@
+use ark_tagger::model::Model;
+use ark_tagger::http::Server;
+use ark_tagger::utils;
+use ark_tagger::error::{Result, WithPath};
+
+use std::path::Path;
+
+pub fn run(mut model: Model, port: u16, token: Option<String>, read_only: bool) -> Result<bool> {
+    super::scan(&mut model);
+    super::import_xattrs(&mut model);
+
+    let token = match token {
+        Some(token) => token,
+        None => utils::random_hex(16).with_path(Path::new("/dev/urandom"))?
+    };
+
+    let server = Server::bind(model, port, token.clone(), read_only)?;
+    println!("Listening on http://{}{}", server.address(),
+        if read_only { " (read-only)" } else { "" });
+    println!("Token: {}", token);
+
+    server.run();
+    Ok(true)
+}
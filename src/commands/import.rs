@@ -0,0 +1,74 @@
+use crate::cli::Source;
+use ark_tagger::model::Model;
+use ark_tagger::config::Config;
+use ark_tagger::import::{Plan, Rules, photo, music, sidecars, tmsu, csv};
+use ark_tagger::error::Result;
+
+pub fn run(mut model: Model, config: &Config, source: Source, apply: bool) -> Result<bool> {
+    //the index is saved only when applying, so changed content
+    //is still found as changed after a preview
+    for error in model.scan() {
+        println!("WARNING: {}", error);
+    }
+    let changes = model.index.take_changes();
+
+    let rules = Rules::new(&config.import);
+    let plan = match source {
+        Source::Photos { derived } =>
+            Plan::collect(&model, &changes, &[], |path| photo::read(path, &rules, derived)),
+        Source::Music =>
+            Plan::collect(&model, &changes, music::NAMESPACES, |path| music::read(path, &rules)),
+        Source::Sidecars =>
+            Plan::collect(&model, &changes, &[], |path| sidecars::read(path, &rules)),
+        Source::Tmsu { database } => {
+            let base = tmsu::base(&database, &model.root);
+            let listed = tmsu::read(&database, &rules)?;
+            Plan::resolve(&mut model, &base, listed)
+        },
+        Source::Csv { file } => {
+            let listed = csv::read(&file, &rules)?;
+            let root = model.root.clone();
+            Plan::resolve(&mut model, &root, listed)
+        },
+    };
+
+    for error in plan.errors.iter() {
+        println!("WARNING: {}", error);
+    }
+    for path in plan.unresolved.iter() {
+        println!("WARNING: {:?} is not a file of the root", path);
+    }
+    for proposal in plan.proposals.iter() {
+        let tags: Vec<String> = proposal.tags.iter().map(|tag| format!("+{}", tag))
+            .chain(proposal.stale.iter().map(|tag| format!("-{}", tag)))
+            .collect();
+        println!("{:?}{}: {}", proposal.path,
+            if proposal.previous.is_some() { " (changed)" } else { "" },
+            tags.join(", "));
+    }
+
+    let created: Vec<&str> = plan.created(&model.database).into_iter()
+        .map(|tag| tag.as_str())
+        .collect();
+    if !plan.unresolved.is_empty() {
+        println!("{} listed paths can't be tagged", plan.unresolved.len());
+    }
+    println!("{} files to tag, new tags: {}", plan.proposals.len(),
+        if created.is_empty() { "none".to_owned() } else { created.join(", ") });
+
+    if !apply {
+        println!("Nothing is tagged yet, run with --apply to tag the files");
+        return Ok(true);
+    }
+
+    let report = plan.apply(&mut model.database)?;
+    for error in model.database.take_errors() {
+        println!("WARNING: {}", error);
+    }
+    if let Err(error) = model.index.save() {
+        println!("WARNING: {}", error);
+    }
+    println!("Tagged {} files, created {} tags, {} tags are gone",
+        report.tagged, report.created.len(), report.removed.len());
+    Ok(true)
+}
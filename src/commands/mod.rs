@@ -0,0 +1,102 @@
+mod fsck;
+mod gc;
+mod verify;
+mod volumes;
+mod daemon;
+mod http;
+mod farm;
+mod xattrs;
+mod import;
+mod sidecars;
+#[cfg(feature = "filesystem")]
+mod mount;
+
+use crate::cli::{Options, Command};
+use ark_tagger::config::Config;
+use ark_tagger::model::{self, Model};
+use ark_tagger::index::MigrationPolicy;
+use ark_tagger::error::{Error, Result};
+
+//commands run without the graphical interface,
+//the returned value is the exit code
+pub fn run(command: Command, options: &Options, config: &Config) -> i32 {
+    let result = match command {
+        Command::Fsck { repair, prune } =>
+            model(options, config).and_then(|mut model| fsck::run(&mut model, repair, prune)),
+        Command::Orphans =>
+            read_only(options, config).and_then(|mut model| gc::report(&mut model)),
+        Command::Gc { grace, force } =>
+            model(options, config).and_then(|mut model| gc::run(&mut model, grace, force)),
+        Command::Verify { quiet } =>
+            read_only(options, config).and_then(|model| verify::run(&model, quiet)),
+        Command::Volumes =>
+            volumes::run(config),
+        Command::Daemon { socket } =>
+            model(options, config).and_then(|model| daemon::run(model, socket)),
+        Command::Farm { path, depth, follow } =>
+            model(options, config).and_then(|model| farm::run(model, path, depth, follow)),
+        Command::Http { port, token, read_only: true } =>
+            read_only(options, config).and_then(|model| http::run(model, port, token, true)),
+        Command::Http { port, token, read_only: false } =>
+            model(options, config).and_then(|model| http::run(model, port, token, false)),
+        Command::Xattrs { import_only } =>
+            model(options, config).and_then(|model| xattrs::run(model, import_only)),
+        Command::Sidecars =>
+            model(options, config).and_then(sidecars::run),
+        Command::Import { apply, source } =>
+            model(options, config).and_then(|model| import::run(model, config, source, apply)),
+        #[cfg(feature = "filesystem")]
+        Command::Mount { mountpoint } =>
+            model(options, config).and_then(|model| mount::run(model, mountpoint)),
+    };
+
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(error) => {
+            println!("ERROR: {}", error);
+            2
+        }
+    }
+}
+
+fn model(options: &Options, config: &Config) -> Result<Model> {
+    let root = options.root.clone().unwrap();
+    Model::new(root, MigrationPolicy::Ignore, config)
+}
+
+//commands which don't change the root don't mark it as a collection
+fn read_only(options: &Options, config: &Config) -> Result<Model> {
+    let root = options.root.clone().unwrap();
+    Model::read_only(root, MigrationPolicy::Ignore, config)
+}
+
+//the whole tree must be indexed before the database can be checked against it,
+//returns the number of directories which couldn't be listed
+fn scan(model: &mut Model) -> usize {
+    let errors = model.scan();
+    for error in errors.iter() {
+        println!("WARNING: {}", error);
+    }
+    if let Err(error) = model.index.save() {
+        println!("WARNING: {}", error);
+    }
+    model::unlisted(&errors)
+}
+
+//only commands serving the root import tags of the attributes,
+//commands checking the database must see it as it is
+fn import_xattrs(model: &mut Model) {
+    for error in model.import_xattrs() {
+        println!("WARNING: {}", error);
+    }
+}
+
+//files of directories which couldn't be listed would be taken for removed,
+//unreadable files are still known by their recorded ids
+fn scan_completely(model: &mut Model) -> Result<()> {
+    match scan(model) {
+        0 => Ok(()),
+        count => Err(Error::Unscanned { count })
+    }
+}
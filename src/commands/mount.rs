@@ -0,0 +1,15 @@
+use ark_tagger::model::Model;
+use ark_tagger::tagfs;
+use ark_tagger::error::Result;
+
+use std::path::PathBuf;
+
+//blocks until unmounted with `fusermount -u`
+pub fn run(mut model: Model, mountpoint: PathBuf) -> Result<bool> {
+    super::scan(&mut model);
+    super::import_xattrs(&mut model);
+
+    println!("Mounting tags of {:?} at {:?}", model.root, mountpoint);
+    tagfs::mount(model, &mountpoint)?;
+    Ok(true)
+}
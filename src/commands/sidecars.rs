@@ -0,0 +1,16 @@
+use ark_tagger::model::Model;
+use ark_tagger::sidecar;
+use ark_tagger::error::Result;
+
+pub fn run(mut model: Model) -> Result<bool> {
+    super::scan(&mut model);
+
+    let mut errors = vec![];
+    let written = sidecar::export(&model, &mut errors);
+
+    for error in errors.iter() {
+        println!("WARNING: {}", error);
+    }
+    println!("{} sidecars written", written);
+    Ok(errors.is_empty())
+}
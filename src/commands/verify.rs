@@ -0,0 +1,31 @@
+use ark_tagger::model::Model;
+use ark_tagger::error::Result;
+
+pub fn run(model: &Model, quiet: bool) -> Result<bool> {
+    let verification = model.verify()?;
+
+    for (path, expected, actual) in verification.corrupted.iter() {
+        println!("CORRUPTED: {:?} (expected {}, found {})",
+            path, expected.to_string(), actual.to_string());
+    }
+    for error in verification.errors.iter() {
+        println!("ERROR: {}", error);
+    }
+
+    if !quiet {
+        for path in verification.modified.iter() {
+            println!("modified: {:?}", path);
+        }
+        for path in verification.missing.iter() {
+            println!("missing: {:?}", path);
+        }
+
+        println!("Verified: {}, modified: {}, missing: {}, corrupted: {}",
+            verification.verified,
+            verification.modified.len(),
+            verification.missing.len(),
+            verification.corrupted.len());
+    }
+
+    Ok(verification.is_clean())
+}
@@ -0,0 +1,18 @@
+use ark_tagger::volume::{self, Registry};
+use ark_tagger::config::Config;
+use ark_tagger::error::Result;
+
+pub fn run(config: &Config) -> Result<bool> {
+    let registry = Registry::load()?;
+
+    for (id, registration) in registry.roots() {
+        println!("{} {:?}{}", id, registration.root,
+            if volume::mounted(id, registration, &config.names.volume) { "" } else { " (not mounted)" });
+
+        if let Some(database) = &registration.database {
+            println!("\tdatabase: {:?}", database);
+        }
+    }
+
+    Ok(true)
+}
@@ -0,0 +1,17 @@
+use ark_tagger::model::Model;
+use ark_tagger::xattrs;
+use ark_tagger::error::Result;
+
+//conflicts are reported, the exit code is 1 if there were any
+pub fn run(mut model: Model, import_only: bool) -> Result<bool> {
+    super::scan(&mut model);
+
+    let mut errors = vec![];
+    let sync = xattrs::sync(&mut model, !import_only, &mut errors);
+
+    for error in errors.iter() {
+        println!("WARNING: {}", error);
+    }
+    println!("{} tags imported, attributes of {} files written", sync.imported, sync.exported);
+    Ok(errors.is_empty())
+}
@@ -0,0 +1,181 @@
+//user configuration, `$XDG_CONFIG_HOME/ark-tagger/config.toml`
+//(usually `~/.config/ark-tagger/config.toml`); every key is optional:
+//
+//  #roots offered in the root switcher, the first one
+//  #is opened when no root is given in the command line
+//  roots = ["/home/me/photos", "/mnt/nas/photos"]
+//
+//  #central database shared by the roots, see `--database`
+//  database = "/home/me/.local/share/ark-tagger/tags"
+//
+//  #gitignore-style patterns applied in every root,
+//  #`.arkignore` files have higher priority
+//  ignore = ["*.tmp", "node_modules/"]
+//
+//  #follow, skip or link
+//  symlinks = "follow"
+//
+//  #cached: files with unchanged size and modification time are not re-hashed
+//  #always: files are re-hashed every time they are listed
+//  hashing = "cached"
+//
+//  #tags of files which content has changed are moved to the new content:
+//  #apply moves them at once, ask offers it, ignore leaves them orphaned
+//  migration = "ask"
+//
+//  #initial order of files: none, size, created, modified or accessed
+//  order = "none"
+//
+//  #sync with the `user.xdg.tags` attribute used by file managers:
+//  #tags are imported when the daemon, the HTTP server, the mount
+//  #or the farm start and, with write_through, written to the files
+//  #on every change; import needs write_through, otherwise
+//  #removed tags would be imported again
+//  [xattrs]
+//  import = false
+//  write_through = false
+//
+//  #keywords found by importers are trimmed, lowercased and their
+//  #whitespace is collapsed, then aliases rename them
+//  [import]
+//  normalize = true
+//  aliases = { "kitty" = "cats", "doggy" = "dogs" }
+//
+//  [window]
+//  width = 480
+//  height = 480
+//
+//  #names of the service files in a root,
+//  #changing them makes existing collections invisible
+//  [names]
+//  data = ".ark-tags.data"
+//  index = ".ark-tags.index"
+//  ignore = ".arkignore"
+//  orphans = ".ark-tags.orphans"
+//  archive = ".ark-tags.archive"
+//  volume = ".ark-tags.volume"
+//
+//command line flags override the file; the file is reloaded
+//by the graphical interface when changed, except `window` and `names`
+
+use crate::index::{Hashing, MigrationPolicy};
+use crate::model::entry::SymlinkPolicy;
+use crate::error::{Error, Result, WithPath};
+
+use serde::Deserialize;
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub roots: Vec<PathBuf>,
+    pub database: Option<PathBuf>,
+    pub ignore: Vec<String>,
+    pub symlinks: SymlinkPolicy,
+    pub hashing: Hashing,
+    pub migration: MigrationPolicy,
+    pub order: Option<String>,
+    pub xattrs: Xattrs,
+    pub import: Import,
+    pub window: Window,
+    pub names: Names,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Xattrs {
+    pub import: bool,
+    pub write_through: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Import {
+    pub normalize: bool,
+    pub aliases: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Window {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Names {
+    pub data: String,
+    pub index: String,
+    pub ignore: String,
+    pub orphans: String,
+    pub archive: String,
+    pub volume: String,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir()
+            .map(|dir| dir.join("ark-tagger").join("config.toml"))
+    }
+
+    //a missing file is the same as an empty one
+    pub fn load() -> Result<Self> {
+        let path = match Self::path() {
+            Some(path) => path,
+            None => return Ok(Config::default())
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Config::default()),
+            Err(error) => return Err(error).with_path(&path)
+        };
+
+        let config: Config = toml::from_str(&content)
+            .map_err(|error| Error::Config { path: path.clone(), message: error.to_string() })?;
+
+        if config.xattrs.import && !config.xattrs.write_through {
+            let message = "xattrs.import needs xattrs.write_through".to_owned();
+            return Err(Error::Config { path, message });
+        }
+        Ok(config)
+    }
+}
+
+impl Names {
+    //service files in the root which are never listed
+    pub fn builtin(&self) -> Vec<String> {
+        vec![self.index.clone(), self.data.clone(), self.orphans.clone(),
+             self.archive.clone(), self.volume.clone()]
+    }
+}
+
+impl Default for Import {
+    fn default() -> Self {
+        Import { normalize: true, aliases: BTreeMap::new() }
+    }
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Window { width: 480, height: 480 }
+    }
+}
+
+impl Default for Names {
+    fn default() -> Self {
+        Names {
+            data: ".ark-tags.data".to_owned(),
+            index: ".ark-tags.index".to_owned(),
+            ignore: ".arkignore".to_owned(),
+            orphans: ".ark-tags.orphans".to_owned(),
+            archive: ".ark-tags.archive".to_owned(),
+            volume: ".ark-tags.volume".to_owned(),
+        }
+    }
+}
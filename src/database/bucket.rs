@@ -1,6 +1,7 @@
 use crate::model::id::Id;
+use crate::error::{Error, Result, WithPath};
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs::{self, File};
 use std::collections::HashSet;
 
@@ -10,44 +11,89 @@ pub struct Bucket {
 }
 
 impl Bucket {
-    pub fn init(path: PathBuf, id: Id) -> Self {
-        let mut ids = HashSet::new();
-        fs::create_dir_all(&path).unwrap();
-        ids.insert(id);
+    pub fn init(path: PathBuf, id: Id) -> Result<Self> {
+        fs::create_dir_all(&path).with_path(&path)?;
 
-        Bucket { path, ids }
+        let mut bucket = Bucket { path, ids: HashSet::new() };
+        bucket.insert(id)?;
+        Ok(bucket)
     }
 
-    pub fn load(path: PathBuf) -> Self {
-        let ids = fs::read_dir(&path)
-            .unwrap()
-            .map(|entry| {
-                let entry = entry.unwrap();
-                debug_assert!(entry.file_type().unwrap().is_file());
-                entry.file_name().to_str().unwrap().parse::<Id>().unwrap()
-            })
-            .collect();
+    //malformed entries are skipped and reported into `errors`
+    pub fn load(path: PathBuf, errors: &mut Vec<Error>) -> Result<Self> {
+        let mut ids = HashSet::new();
+
+        for entry in fs::read_dir(&path).with_path(&path)? {
+            let id = entry.with_path(&path)
+                .and_then(|entry| Self::parse(entry));
+
+            match id {
+                Ok(id) => { ids.insert(id); },
+                Err(error) => errors.push(error)
+            }
+        }
+
+        Ok(Bucket { path, ids })
+    }
 
-        Bucket { path, ids }
+    pub fn path(&self) -> &Path {
+        &self.path
     }
 
     pub fn values(&self) -> &HashSet<Id> {
         &self.ids
     }
 
-    pub fn insert_all<I>(&mut self, ids: I)
+    pub fn insert_all<I>(&mut self, ids: I) -> Result<()>
     where I: Iterator<Item = Id> {
-        ids.for_each(|id| self.insert(id))
+        for id in ids {
+            self.insert(id)?;
+        }
+        Ok(())
     }
 
-    fn insert(&mut self, id: Id) {
-        if !self.ids.contains(&id) {
-            self.ids.insert(id);
+    pub fn remove(&mut self, id: Id) -> Result<bool> {
+        if self.ids.remove(&id) {
+            let mut path = self.path.clone();
+            path.push(id.to_string());
+
+            fs::remove_file(&path).with_path(&path)?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
 
+    pub fn insert(&mut self, id: Id) -> Result<()> {
+        if !self.ids.contains(&id) {
             let mut path = self.path.clone();
             path.push(id.to_string());
 
-            File::create(&path).unwrap();
+            File::create(&path).with_path(&path)?;
+            self.ids.insert(id);
         }
+        Ok(())
     }
-}
\ No newline at end of file
+
+    //the bucket must be empty
+    pub fn delete(self) -> Result<()> {
+        debug_assert!(self.ids.is_empty());
+        fs::remove_dir(&self.path).with_path(&self.path)
+    }
+
+    pub(super) fn parse(entry: fs::DirEntry) -> Result<Id> {
+        let path = entry.path();
+
+        let file_type = entry.file_type().with_path(&path)?;
+        if !file_type.is_file() {
+            return Err(Error::UnexpectedEntry { path });
+        }
+
+        let name = entry.file_name();
+        let name = name.to_str()
+            .ok_or_else(|| Error::Encoding { path: path.clone() })?;
+
+        name.parse::<Id>()
+            .map_err(|source| Error::MalformedId { path, source })
+    }
+}
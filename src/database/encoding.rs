@@ -0,0 +1,114 @@
+use crate::model::tag::Tag;
+use crate::error::{Error, Result};
+
+//tags are stored as names of directories, so they are encoded
+//to contain only characters allowed by any filesystem;
+//upper-case letters are escaped as well because of case-insensitive filesystems
+
+//limit of names of most filesystems, in bytes
+const NAME_MAX: usize = 255;
+
+//escaping triples non-ASCII bytes, so long tags don't fit into a name
+pub fn encode(tag: &Tag) -> Result<String> {
+    //an empty name would be the database directory itself
+    if tag.is_empty() {
+        return Err(Error::EmptyTag);
+    }
+
+    let mut result = String::with_capacity(tag.len());
+    for byte in tag.bytes() {
+        match byte {
+            b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => result.push(byte as char),
+            b'.' if !result.is_empty() => result.push('.'),
+            _ => result.push_str(&format!("%{:02X}", byte))
+        }
+    }
+
+    if result.len() > NAME_MAX {
+        return Err(Error::TagTooLong { tag: tag.clone() });
+    }
+    Ok(result)
+}
+
+//names without escape sequences are decoded as is,
+//so databases created before the encoding was introduced can be read
+pub fn decode(name: &str) -> Option<Tag> {
+    let bytes = name.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' && i + 2 < bytes.len() {
+            std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+
+        match escaped {
+            Some(byte) => {
+                result.push(byte);
+                i += 3;
+            },
+            None => {
+                result.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8(result).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(tag: &str) -> String {
+        let name = encode(&tag.to_owned()).unwrap();
+        assert_eq!(decode(&name), Some(tag.to_owned()));
+        name
+    }
+
+    #[test]
+    fn plain_tags_are_kept() {
+        assert_eq!(round_trip("cats"), "cats");
+        assert_eq!(round_trip("2020-summer_trip.old"), "2020-summer_trip.old");
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert_eq!(round_trip(".hidden"), "%2Ehidden");
+        assert_eq!(round_trip("100%"), "100%25");
+        assert_eq!(round_trip("camera/Nikon"), "camera%2F%4Eikon");
+        assert_eq!(round_trip("кот"), "%D0%BA%D0%BE%D1%82");
+    }
+
+    #[test]
+    fn empty_tags_are_rejected() {
+        assert!(matches!(encode(&String::new()), Err(Error::EmptyTag)));
+    }
+
+    #[test]
+    fn long_tags_are_rejected() {
+        let longest = "a".repeat(NAME_MAX);
+        assert_eq!(round_trip(&longest).len(), NAME_MAX);
+        assert!(matches!(encode(&"a".repeat(NAME_MAX + 1)), Err(Error::TagTooLong { .. })));
+
+        //every byte of a non-ASCII character takes three
+        let escaped = "ж".repeat(NAME_MAX / 6 + 1);
+        assert!(matches!(encode(&escaped), Err(Error::TagTooLong { .. })));
+    }
+
+    #[test]
+    fn non_utf8_names_are_not_decoded() {
+        assert_eq!(decode("%FF%FE"), None);
+        assert_eq!(decode("%D0"), None);
+    }
+
+    #[test]
+    fn legacy_names_are_decoded_as_is() {
+        assert_eq!(decode("Cats"), Some("Cats".to_owned()));
+        assert_eq!(decode("50%off"), Some("50%off".to_owned()));
+    }
+}
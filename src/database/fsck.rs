@@ -0,0 +1,163 @@
+use crate::model::id::Id;
+use crate::model::tag::Tag;
+use crate::error::{Error, Result, WithPath};
+use super::bucket::Bucket;
+use super::encoding;
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::collections::HashSet;
+
+#[derive(Debug, Default)]
+pub struct Report {
+    //entries which can't be loaded as buckets or ids
+    pub malformed: Vec<Error>,
+    //buckets named before the tags encoding was introduced
+    pub legacy: Vec<PathBuf>,
+    //legacy buckets holding the same tag as an encoded one
+    pub collisions: Vec<PathBuf>,
+    pub empty: Vec<Tag>,
+    //ids which no indexed file has
+    pub unknown: Vec<(Tag, Id)>,
+
+    pub changes: Vec<String>,
+}
+
+impl Report {
+    pub fn is_clean(&self) -> bool {
+        self.malformed.is_empty() && self.legacy.is_empty() && self.collisions.is_empty() &&
+            self.empty.is_empty() && self.unknown.is_empty()
+    }
+}
+
+//scans all buckets of the database located at `path`;
+//with `repair` malformed entries and empty buckets are removed
+//and legacy buckets are renamed or merged into encoded buckets of the same tag,
+//with `prune` unknown ids are removed too
+pub fn check(path: &Path, known: &HashSet<Id>, repair: bool, prune: bool) -> Result<Report> {
+    let mut report = Report::default();
+
+    let directory = match fs::read_dir(path) {
+        Ok(directory) => directory,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(report),
+        Err(error) => return Err(error).with_path(path)
+    };
+
+    for entry in directory {
+        let entry = entry.with_path(path)?;
+        let bucket = entry.path();
+
+        if !entry.file_type().with_path(&bucket)?.is_dir() {
+            report.malformed.push(Error::UnexpectedEntry { path: bucket.clone() });
+            if repair {
+                remove(&bucket, &mut report)?;
+            }
+            continue;
+        }
+
+        let name = entry.file_name();
+        let tag = match name.to_str().and_then(encoding::decode) {
+            Some(tag) => tag,
+            None => {
+                //the tag can't be recovered, so the bucket is left as is
+                report.malformed.push(Error::Encoding { path: bucket });
+                continue;
+            }
+        };
+
+        let ids = check_bucket(&bucket, &tag, known, repair, prune, &mut report)?;
+        if ids == 0 {
+            report.empty.push(tag.clone());
+            if repair {
+                fs::remove_dir(&bucket).with_path(&bucket)?;
+                report.changes.push(format!("removed empty bucket {:?}", tag));
+                continue;
+            }
+        }
+
+        let encoded = match encoding::encode(&tag) {
+            Ok(encoded) => encoded,
+            Err(error) => {
+                //the legacy name can't be encoded, so it is left as is
+                report.malformed.push(error);
+                continue;
+            }
+        };
+        if name.to_str() != Some(encoded.as_str()) {
+            report.legacy.push(bucket.clone());
+
+            let target = path.join(encoded);
+            if target.exists() {
+                report.collisions.push(bucket.clone());
+                if repair {
+                    merge(&bucket, &target, &mut report)?;
+                }
+            } else if repair {
+                fs::rename(&bucket, &target).with_path(&bucket)?;
+                report.changes.push(format!("renamed {:?} into {:?}", bucket, target));
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn check_bucket(bucket: &Path, tag: &Tag, known: &HashSet<Id>, repair: bool, prune: bool,
+                report: &mut Report) -> Result<usize> {
+    let mut ids = 0;
+
+    for entry in fs::read_dir(bucket).with_path(bucket)? {
+        let entry = entry.with_path(bucket)?;
+        let path = entry.path();
+
+        match Bucket::parse(entry) {
+            Ok(id) if known.contains(&id) => ids += 1,
+            Ok(id) => {
+                report.unknown.push((tag.clone(), id));
+                if prune {
+                    remove(&path, report)?;
+                } else {
+                    ids += 1;
+                }
+            },
+            Err(error) => {
+                report.malformed.push(error);
+                if repair {
+                    remove(&path, report)?;
+                }
+            }
+        }
+    }
+
+    Ok(ids)
+}
+
+//ids are moved into `target`, then `bucket` is removed
+fn merge(bucket: &Path, target: &Path, report: &mut Report) -> Result<()> {
+    for entry in fs::read_dir(bucket).with_path(bucket)? {
+        let entry = entry.with_path(bucket)?;
+        let moved = target.join(entry.file_name());
+        if moved.exists() {
+            fs::remove_file(entry.path()).with_path(&entry.path())?;
+        } else {
+            fs::rename(entry.path(), &moved).with_path(&entry.path())?;
+        }
+    }
+
+    fs::remove_dir(bucket).with_path(bucket)?;
+    report.changes.push(format!("merged {:?} into {:?}", bucket, target));
+    Ok(())
+}
+
+fn remove(path: &Path, report: &mut Report) -> Result<()> {
+    let meta = fs::symlink_metadata(path).with_path(path)?;
+    if meta.is_dir() {
+        fs::remove_dir_all(path).with_path(path)?;
+    } else {
+        fs::remove_file(path).with_path(path)?;
+    }
+
+    report.changes.push(format!("removed {:?}", path));
+    Ok(())
+}
@@ -1,8 +1,12 @@
 mod bucket;
+pub mod encoding;
+pub mod fsck;
+pub mod orphans;
 
 use crate::model::id::Id;
 use crate::model::tag::{Tag, HighlightedTag};
 use crate::utils::Filter;
+use crate::error::{Error, Result, WithPath};
 
 use bucket::Bucket;
 
@@ -11,63 +15,232 @@ use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::collections::{BTreeMap, HashSet};
 
+/// Tags and ids of tagged files, one directory (bucket) per tag.
 pub struct Database {
     path: PathBuf,
 
     //BTreeMap is used because keys should be sorted when retrieved
-    bucket_by_tag: BTreeMap<Tag, Bucket>
+    bucket_by_tag: BTreeMap<Tag, Bucket>,
+
+    //malformed entries are skipped while loading
+    errors: Vec<Error>,
+
+    //ids tagged or untagged since `take_modified`, tracked only with write-through
+    modified: Option<HashSet<Id>>
 }
 
 impl Database {
-    pub fn new(path: PathBuf) -> Self {
-        let bucket_by_tag = Self::scan_buckets(&path);
-        Database { path, bucket_by_tag }
+    /// Loads the database from `path`; a missing directory is an empty database.
+    /// Malformed entries are skipped and available from `take_errors`.
+    pub fn new(path: PathBuf) -> Result<Self> {
+        let mut errors = vec![];
+        let bucket_by_tag = Self::scan_buckets(&path, &mut errors)?;
+        Ok(Database { path, bucket_by_tag, errors, modified: None })
     }
 
-    pub fn insert<I>(&mut self, ids: I, tag: &Tag) -> bool
+    /// Loads the buckets again, e.g. when another process has changed them;
+    /// write-through is kept.
+    pub fn reload(&mut self) -> Result<()> {
+        self.bucket_by_tag = Self::scan_buckets(&self.path, &mut self.errors)?;
+        Ok(())
+    }
+
+    /// Tracks changed ids, so tags can be written through to the files.
+    pub fn with_write_through(mut self) -> Self {
+        self.modified = Some(HashSet::new());
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Non-fatal errors collected since the previous call.
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::replace(&mut self.errors, vec![])
+    }
+
+    /// Ids tagged or untagged since the previous call,
+    /// always empty without write-through.
+    pub fn take_modified(&mut self) -> HashSet<Id> {
+        self.modified.as_mut()
+            .map(|modified| std::mem::replace(modified, HashSet::new()))
+            .unwrap_or_default()
+    }
+
+    fn touch(&mut self, ids: &[Id]) {
+        if let Some(modified) = &mut self.modified {
+            modified.extend(ids.iter().copied());
+        }
+    }
+
+    /// Tags `ids` with `tag`, returns `true` if the tag is new.
+    pub fn insert<I>(&mut self, ids: I, tag: &Tag) -> Result<bool>
         where I: Iterator<Item = Id> {
 
+        let ids: Vec<Id> = ids.collect();
+        self.touch(&ids);
+        let ids = ids.into_iter();
+
         let bucket = self.bucket_by_tag.get_mut(tag);
         match bucket {
             Some(bucket) => {
-                bucket.insert_all(ids);
+                bucket.insert_all(ids)?;
 
-                false
+                Ok(false)
             },
             None => {
+                let mut ids = ids.peekable();
+                if ids.peek().is_none() {
+                    return Ok(false);
+                }
+
                 let mut path = self.path.clone();
-                path.push(tag);
+                path.push(encoding::encode(tag)?);
 
-                let mut ids = ids;
-                let mut bucket = Bucket::init(path, ids.next().unwrap());
-                bucket.insert_all(ids);
+                let mut bucket = Bucket::init(path, ids.next().unwrap())?;
+                bucket.insert_all(ids)?;
 
                 let old = self.bucket_by_tag.insert(tag.clone(), bucket);
                 debug_assert!(old.is_none());
-                true
+                Ok(true)
             }
         }
     }
 
+    /// Removes `tag` from `ids`, returns `true` if the tag is gone
+    /// since nothing is tagged with it anymore.
+    pub fn remove<I>(&mut self, ids: I, tag: &Tag) -> Result<bool>
+        where I: Iterator<Item = Id> {
+
+        let ids: Vec<Id> = ids.collect();
+        self.touch(&ids);
+
+        let bucket = match self.bucket_by_tag.get_mut(tag) {
+            Some(bucket) => bucket,
+            None => return Ok(false)
+        };
+
+        for id in ids {
+            bucket.remove(id)?;
+        }
+
+        if bucket.values().is_empty() {
+            let bucket = self.bucket_by_tag.remove(tag).unwrap();
+            bucket.delete()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// All tags with tagged ids, sorted by tag.
+    pub fn buckets(&self) -> impl Iterator<Item = (&Tag, &HashSet<Id>)> {
+        self.bucket_by_tag.iter()
+            .map(|(tag, bucket)| (tag, bucket.values()))
+    }
+
+    /// All tags with numbers of tagged ids, sorted.
+    pub fn counts(&self) -> impl Iterator<Item = (&Tag, usize)> {
+        self.bucket_by_tag.iter()
+            .map(|(tag, bucket)| (tag, bucket.values().len()))
+    }
+
+    /// Whether anything is tagged with `tag`.
+    pub fn contains(&self, tag: &Tag) -> bool {
+        self.bucket_by_tag.contains_key(tag)
+    }
+
+    /// Tagged ids which are not in `known`, with their tags.
+    pub fn orphans(&self, known: &HashSet<Id>) -> BTreeMap<Id, Vec<Tag>> {
+        let mut orphans: BTreeMap<Id, Vec<Tag>> = BTreeMap::new();
+
+        for (tag, bucket) in self.bucket_by_tag.iter() {
+            for id in bucket.values().difference(known) {
+                orphans.entry(*id).or_default().push(tag.clone());
+            }
+        }
+
+        orphans
+    }
+
+    /// Removes `id` from all buckets, buckets left empty are removed too;
+    /// returns the removed tags.
+    pub fn forget(&mut self, id: Id) -> Result<Vec<Tag>> {
+        let mut tags = vec![];
+
+        for (tag, bucket) in self.bucket_by_tag.iter_mut() {
+            if bucket.remove(id)? {
+                tags.push(tag.clone());
+            }
+        }
+
+        let empty: Vec<Tag> = self.bucket_by_tag.iter()
+            .filter(|(_, bucket)| bucket.values().is_empty())
+            .map(|(tag, _)| tag.clone())
+            .collect();
+
+        for tag in empty {
+            let bucket = self.bucket_by_tag.remove(&tag).unwrap();
+            bucket.delete()?;
+        }
+
+        Ok(tags)
+    }
+
+    /// Tags of `id`, sorted.
+    pub fn tags_of(&self, id: Id) -> impl Iterator<Item = &Tag> {
+        self.bucket_by_tag.iter()
+            .filter(move |(_, bucket)| bucket.values().contains(&id))
+            .map(|(tag, _)| tag)
+    }
+
+    /// Moves (or copies, if `keep_old`) all tags of `old` to `new`.
+    pub fn migrate(&mut self, old: Id, new: Id, keep_old: bool) -> Result<Vec<Tag>> {
+        let mut migrated = vec![];
+
+        for (tag, bucket) in self.bucket_by_tag.iter_mut() {
+            if bucket.values().contains(&old) {
+                bucket.insert(new)?;
+                if !keep_old {
+                    bucket.remove(old)?;
+                }
+                migrated.push(tag.clone());
+            }
+        }
+
+        Ok(migrated)
+    }
+
     //todo: implement inversion and "fresh" pseudo-tag
     //todo: implement union filters and combinations of unions/intersections
+    /// For every id, whether it has all of `tags`.
     pub fn filter<'b, I, T>(&self, ids: I, tags: T) -> Filter
-        where I: Iterator<Item = Id>,
+        where I: Iterator<Item = Option<Id>>,
               T: Iterator<Item = &'b Tag> {
-        let ids: Vec<Id> = ids.collect();
+        let ids: Vec<Option<Id>> = ids.collect();
+        let tags: Vec<&Tag> = tags.collect();
 
-        let matches: HashSet<Id> = tags.fold(
-            ids.iter().cloned().collect(),
+        let matches: HashSet<Id> = tags.iter().fold(
+            ids.iter().flatten().cloned().collect(),
             |acc, tag|
-                acc.intersection(&self.bucket_by_tag.get(tag).unwrap().values())
-                    .cloned()
-                    .collect());
+                match self.bucket_by_tag.get(*tag) {
+                    Some(bucket) => acc.intersection(bucket.values())
+                        .cloned()
+                        .collect(),
+                    //nothing is tagged with an unknown tag
+                    None => HashSet::new()
+                });
 
+        //files without ids can't be tagged
         ids.iter()
-            .map(|id| matches.contains(id))
+            .map(|id| match id {
+                Some(id) => matches.contains(id),
+                None => tags.is_empty()
+            })
             .collect()
     }
 
+    /// All tags, highlighted if any of `ids` has them.
     pub fn sieved_tags<I>(&self, ids: I) -> impl Iterator<Item = HighlightedTag>
         where I: Iterator<Item = Id> {
         let ids: HashSet<Id> = ids.collect();
@@ -82,36 +255,53 @@ impl Database {
                 })
     }
 
+    /// Same as `sieved_tags`, only highlighting.
     pub fn sieve<'a, I: 'a>(&'a self, ids: I) -> impl Iterator<Item = bool> + 'a
         where I: Iterator<Item = Id> {
         self.sieved_tags(ids).map(|HighlightedTag { highlighted, tag: _}| highlighted)
     }
 
-    fn scan_buckets(path: &Path) -> BTreeMap<Tag, Bucket> {
-        println!("{:?}", path);
+    fn scan_buckets(path: &Path, errors: &mut Vec<Error>) -> Result<BTreeMap<Tag, Bucket>> {
+        crate::trace!("{:?}", path);
         let directory = fs::read_dir(&path);
 
         match directory {
             Err(error) => {
                 match error.kind() {
                     ErrorKind::NotFound => {
-                        println!("There is no database yet");
-                        return BTreeMap::new();
+                        crate::trace!("There is no database yet");
+                        Ok(BTreeMap::new())
                     },
-                    _ => panic!(error.to_string())
+                    _ => Err(error).with_path(path)
                 }
             },
 
             Ok(directory) => {
-                directory.map(|entry| {
-                    let entry = entry.unwrap();
+                let mut buckets = BTreeMap::new();
 
-                    let bucket = Bucket::load(entry.path());
-                    let tag = entry.file_name().into_string().unwrap();
+                for entry in directory {
+                    let bucket = entry.with_path(path)
+                        .and_then(|entry| {
+                            let tag = entry.file_name().to_str()
+                                .and_then(encoding::decode)
+                                .ok_or_else(|| Error::Encoding { path: entry.path() })?;
+                            let bucket = Bucket::load(entry.path(), errors)?;
 
-                    (tag, bucket)
-                }).collect()
+                            Ok((tag, bucket))
+                        });
+
+                    //a legacy bucket and an encoded one can hold the same tag,
+                    //the first one is kept until fsck merges them
+                    match bucket {
+                        Ok((tag, bucket)) if buckets.contains_key(&tag) =>
+                            errors.push(Error::Collision { path: bucket.path().to_path_buf(), tag }),
+                        Ok((tag, bucket)) => { buckets.insert(tag, bucket); },
+                        Err(error) => errors.push(error)
+                    }
+                }
+
+                Ok(buckets)
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,67 @@
+use crate::model::id::Id;
+use crate::error::{Error, Result, WithPath};
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::collections::BTreeMap;
+
+//ids which are present in the database but absent in the index,
+//with time (in seconds since unix epoch) when they were noticed first
+pub struct Orphans {
+    path: PathBuf,
+    since: BTreeMap<Id, u64>
+}
+
+impl Orphans {
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error).with_path(&path)
+        };
+
+        let mut since = BTreeMap::new();
+        for line in content.lines().filter(|line| !line.is_empty()) {
+            let mut parts = line.split(' ');
+
+            let id = parts.next().unwrap_or_default();
+            let id = id.parse::<Id>()
+                .map_err(|source| Error::MalformedId { path: path.clone(), source })?;
+
+            let time = parts.next()
+                .and_then(|time| time.parse::<u64>().ok())
+                .ok_or_else(|| Error::UnexpectedEntry { path: path.clone() })?;
+
+            since.insert(id, time);
+        }
+
+        Ok(Orphans { path, since })
+    }
+
+    //ids which are not orphans anymore are forgotten
+    pub fn update<I>(&mut self, orphans: I, now: u64)
+    where I: Iterator<Item = Id> {
+        let mut since = BTreeMap::new();
+        for id in orphans {
+            since.insert(id, self.since.get(&id).copied().unwrap_or(now));
+        }
+        self.since = since;
+    }
+
+    pub fn since(&self, id: Id) -> Option<u64> {
+        self.since.get(&id).copied()
+    }
+
+    pub fn forget(&mut self, id: Id) {
+        self.since.remove(&id);
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content: String = self.since.iter()
+            .map(|(id, time)| format!("{} {}\n", id.to_string(), time))
+            .collect();
+
+        fs::write(&self.path, content).with_path(&self.path)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::model::id::ParseError;
+
+use derive_more::Display;
+use std::path::{Path, PathBuf};
+use std::io;
+
+#[derive(Debug, Display)]
+pub enum Error {
+    #[display(fmt = "{:?}: {}", path, source)]
+    Io { path: PathBuf, source: io::Error },
+
+    #[display(fmt = "{:?}: malformed id {:?}", path, source)]
+    MalformedId { path: PathBuf, source: ParseError },
+
+    #[display(fmt = "{:?}: the name is not valid unicode", path)]
+    Encoding { path: PathBuf },
+
+    #[display(fmt = "{:?}: the tag is too long to be stored", tag)]
+    TagTooLong { tag: String },
+
+    #[display(fmt = "the tag is empty")]
+    EmptyTag,
+
+    #[display(fmt = "{:?}: another bucket holds the same tag {:?}", path, tag)]
+    Collision { path: PathBuf, tag: String },
+
+    #[display(fmt = "{:?}: the directory can't be listed ({})", path, source)]
+    Unlisted { path: PathBuf, source: io::Error },
+
+    #[display(fmt = "{:?}: directory has been visited already, symlink loop?", path)]
+    Loop { path: PathBuf },
+
+    #[display(fmt = "{:?}: unexpected entry", path)]
+    UnexpectedEntry { path: PathBuf },
+
+    #[display(fmt = "{:?}: malformed ignore rules ({})", path, message)]
+    Ignore { path: PathBuf, message: String },
+
+    #[display(fmt = "{} directories of the tree couldn't be listed, nothing is removed", count)]
+    Unscanned { count: usize },
+
+    #[display(fmt = "{:?}: the collection sharing the database is not mounted", path)]
+    Unmounted { path: PathBuf },
+
+    #[display(fmt = "{:?}: malformed configuration ({})", path, message)]
+    Config { path: PathBuf, message: String },
+
+    #[display(fmt = "{:?}: is outside of the root", path)]
+    OutsideRoot { path: PathBuf },
+
+    #[display(fmt = "daemon: {}", message)]
+    Daemon { message: String },
+
+    #[display(fmt = "{:?}: can't be used for links by tag ({})", path, message)]
+    Farm { path: PathBuf, message: String },
+
+    #[display(fmt = "http: {}", message)]
+    Http { message: String },
+
+    #[display(fmt = "{:?}: `user.xdg.tags` is out of sync ({})", path, message)]
+    Xattr { path: PathBuf, message: String },
+
+    #[display(fmt = "{:?}: malformed metadata ({})", path, message)]
+    Metadata { path: PathBuf, message: String },
+
+    #[display(fmt = "{:?}: can't be imported ({})", path, message)]
+    Import { path: PathBuf, message: String },
+
+    #[display(fmt = "{:?}: can't be opened ({})", path, message)]
+    Open { path: PathBuf, message: String },
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+//attaches the path being processed to an i/o error
+pub trait WithPath<T> {
+    fn with_path(self, path: &Path) -> Result<T>;
+}
+
+impl<T> WithPath<T> for io::Result<T> {
+    fn with_path(self, path: &Path) -> Result<T> {
+        self.map_err(|source| Error::Io { path: path.to_path_buf(), source })
+    }
+}
@@ -0,0 +1,238 @@
+//! Directory of symbolic links by tag, so any program can browse by tag:
+//! `cats/` links to files tagged with "cats", `cats+dogs/` to files
+//! tagged with both. Tags are encoded as bucket names of the database,
+//! so `+` can't be confused with a tag's own characters.
+
+use crate::model::{Model, id::Id, tag::Tag};
+use crate::database::encoding;
+use crate::error::{Error, Result, WithPath};
+
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap};
+
+//marks a directory as a farm, nothing else is ever cleaned up
+const MARKER: &str = ".ark-tags.farm";
+
+pub struct Farm {
+    path: PathBuf
+}
+
+#[derive(Debug, Default)]
+pub struct Synchronization {
+    pub created: usize,
+    pub removed: usize,
+}
+
+//directory name -> link name -> target
+type Layout = BTreeMap<String, BTreeMap<String, PathBuf>>;
+
+impl Farm {
+    /// Opens an existing farm or creates one in a missing or empty directory,
+    /// which must be outside of `root`.
+    pub fn open(path: PathBuf, root: &Path) -> Result<Self> {
+        //checked before anything is created, the directory may not exist yet
+        if canonical(&path).with_path(&path)?.starts_with(root) {
+            let message = "must be outside of the root".to_owned();
+            return Err(Error::Farm { path, message });
+        }
+        fs::create_dir_all(&path).with_path(&path)?;
+
+        let marker = path.join(MARKER);
+        if !marker.exists() {
+            let empty = fs::read_dir(&path).with_path(&path)?.next().is_none();
+            if !empty {
+                let message = "neither empty nor a farm".to_owned();
+                return Err(Error::Farm { path, message });
+            }
+            fs::write(&marker, "").with_path(&marker)?;
+        }
+
+        let path = path.canonicalize().with_path(&path)?;
+        Ok(Farm { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Creates missing links and removes stale ones, links which are
+    /// already in place are not touched. Combinations of up to `depth`
+    /// tags get their own directories; the whole tree must be scanned.
+    pub fn sync(&self, model: &Model, depth: usize) -> Result<Synchronization> {
+        let layout = Self::layout(model, depth);
+        let mut sync = Synchronization::default();
+
+        for entry in fs::read_dir(&self.path).with_path(&self.path)? {
+            let entry = entry.with_path(&self.path)?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name == MARKER || !entry.file_type().with_path(&entry.path())?.is_dir() {
+                continue;
+            }
+
+            let wanted = layout.get(&name);
+            sync.removed += Self::clean(&entry.path(), wanted)?;
+        }
+
+        for (directory, links) in layout.iter() {
+            let directory = self.path.join(directory);
+            fs::create_dir_all(&directory).with_path(&directory)?;
+
+            for (name, target) in links.iter() {
+                let link = directory.join(name);
+                match fs::read_link(&link) {
+                    Ok(existing) if existing == *target => continue,
+                    Ok(_) => fs::remove_file(&link).with_path(&link)?,
+                    Err(error) if error.kind() == ErrorKind::NotFound => {},
+                    Err(error) => return Err(error).with_path(&link)
+                }
+
+                symlink(target, &link).with_path(&link)?;
+                sync.created += 1;
+            }
+        }
+
+        Ok(sync)
+    }
+
+    //removes links which are not wanted, and the directory itself if it is left empty;
+    //anything but symbolic links is kept
+    fn clean(directory: &Path, wanted: Option<&BTreeMap<String, PathBuf>>) -> Result<usize> {
+        let mut removed = 0;
+
+        for entry in fs::read_dir(directory).with_path(directory)? {
+            let entry = entry.with_path(directory)?;
+            let path = entry.path();
+            if !entry.file_type().with_path(&path)?.is_symlink() {
+                continue;
+            }
+
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let target = fs::read_link(&path).with_path(&path)?;
+            let stale = wanted
+                .and_then(|links| links.get(&name))
+                .map(|wanted| *wanted != target)
+                .unwrap_or(true);
+
+            if stale {
+                fs::remove_file(&path).with_path(&path)?;
+                removed += 1;
+            }
+        }
+
+        if wanted.is_none() {
+            if let Err(error) = fs::remove_dir(directory) {
+                println!("WARNING: {:?} is kept: {}", directory, error);
+            }
+        }
+        Ok(removed)
+    }
+
+    fn layout(model: &Model, depth: usize) -> Layout {
+        let mut tags_by_id: HashMap<Id, Vec<&Tag>> = HashMap::new();
+        for (tag, ids) in model.database.buckets() {
+            for id in ids {
+                tags_by_id.entry(*id).or_default().push(tag);
+            }
+        }
+
+        //tagged files which aren't in this root are skipped,
+        //files are ordered to name links the same way every time
+        let mut files: Vec<(Vec<&PathBuf>, Id, Vec<&Tag>)> = tags_by_id.into_iter()
+            .filter_map(|(id, tags)| {
+                let paths: Vec<&PathBuf> = model.index.paths(id).collect();
+                if paths.is_empty() { None } else { Some((paths, id, tags)) }
+            })
+            .collect();
+        files.sort();
+
+        let mut layout = Layout::new();
+        for (paths, id, tags) in files {
+            for combination in combinations(&tags, depth) {
+                //tags too long for a name have no directories
+                let directory: Result<Vec<String>> = combination.iter()
+                    .map(|tag| encoding::encode(tag))
+                    .collect();
+                let directory = match directory {
+                    Ok(directory) => directory.join("+"),
+                    Err(_) => continue
+                };
+                let links = layout.entry(directory).or_default();
+
+                for path in paths.iter() {
+                    links.insert(link_name(links, path, id), path.to_path_buf());
+                }
+            }
+        }
+        layout
+    }
+}
+
+//files with the same name in different directories get the id as a prefix,
+//copies of the same file with the same name also get a counter
+pub(crate) fn link_name(links: &BTreeMap<String, PathBuf>, path: &Path, id: Id) -> String {
+    let name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    //the counter goes before the name, so the extension is kept
+    let id = id.to_string();
+    let mut candidates = std::iter::once(name.clone())
+        .chain(std::iter::once(format!("{}-{}", id, name)))
+        .chain((2..).map(|counter| format!("{}-{}-{}", id, counter, name)));
+
+    //there are always more candidates
+    candidates
+        .find(|candidate| links.get(candidate)
+            .map(|existing| existing == path)
+            .unwrap_or(true))
+        .unwrap()
+}
+
+//absolute path with links resolved, missing directories are appended as they are
+fn canonical(path: &Path) -> std::io::Result<PathBuf> {
+    let path = std::env::current_dir()?.join(path);
+    let mut existing = path.as_path();
+    let mut missing = vec![];
+
+    loop {
+        match existing.canonicalize() {
+            Ok(canonical) => {
+                let path = missing.into_iter().rev()
+                    .fold(canonical, |path, name| path.join(name));
+                return Ok(path);
+            },
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                match (existing.parent(), existing.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        missing.push(name);
+                        existing = parent;
+                    },
+                    _ => return Err(error)
+                }
+            },
+            Err(error) => return Err(error)
+        }
+    }
+}
+
+//non-empty combinations of at most `depth` tags, tags are sorted already
+fn combinations<'a>(tags: &[&'a Tag], depth: usize) -> Vec<Vec<&'a Tag>> {
+    let mut result: Vec<Vec<&Tag>> = vec![vec![]];
+    for tag in tags {
+        let extended: Vec<Vec<&Tag>> = result.iter()
+            .filter(|combination| combination.len() < depth)
+            .map(|combination| {
+                let mut combination = combination.clone();
+                combination.push(*tag);
+                combination
+            })
+            .collect();
+        result.extend(extended);
+    }
+
+    result.retain(|combination| !combination.is_empty());
+    result
+}
@@ -1,39 +1,53 @@
 use crate::model::id::Id;
+use crate::error::{Result, WithPath};
 use crate::utils::measure;
 
 use crc32fast::Hasher;
 use std::path::Path;
-use std::fs::File;
-use std::io::Read;
+use std::fs::{self, File};
+use std::io::{self, Read};
 
-pub fn id(path: &Path) -> Id {
-    println!("\t\tpath = {:?}", path);
-    let mut file = File::open(path).unwrap();
+pub fn id(path: &Path) -> Result<Id> {
+    crate::trace!("\t\tpath = {:?}", path);
+    let mut file = File::open(path).with_path(path)?;
 
-    let size = size(&file);
-    let hash = crc32(&mut file);
+    let size = size(&file).with_path(path)?;
+    let hash = crc32(&mut file).with_path(path)?;
 
-    Id { size, hash }
+    Ok(Id { size, hash })
 }
 
-fn size(file: &File) -> u64 {
+fn size(file: &File) -> io::Result<u64> {
     measure("size", || {
-        file.metadata().unwrap().len()
+        Ok(file.metadata()?.len())
     })
 }
 
 // in case of collisions, try sha1
-fn crc32(file: &mut File) -> u32 {
+fn crc32(file: &mut File) -> io::Result<u32> {
     measure("crc32", || {
         let mut hasher = Hasher::new();
         //use reset() method when it will become more serious
 
         let mut buffer: Vec<u8> = vec![0; 512 * 1024];
-        while let Ok(n) = file.read(&mut buffer) {
+        loop {
+            let n = file.read(&mut buffer)?;
             if n == 0 { break; }
-            hasher.update(&buffer);
+            hasher.update(&buffer[..n]);
         }
 
-        hasher.finalize()
+        Ok(hasher.finalize())
     })
-}
\ No newline at end of file
+}
+
+//the link itself is identified by its target, not by content of the target
+pub fn link_id(path: &Path) -> Result<Id> {
+    crate::trace!("\t\tlink = {:?}", path);
+    let target = fs::read_link(path).with_path(path)?;
+    let target = target.to_string_lossy();
+
+    let mut hasher = Hasher::new();
+    hasher.update(target.as_bytes());
+
+    Ok(Id { size: target.len() as u64, hash: hasher.finalize() })
+}
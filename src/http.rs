@@ -0,0 +1,305 @@
+//! JSON API over HTTP, bound to localhost.
+//!
+//! Every request must carry `Authorization: Bearer <token>`.
+//!
+//! * `GET /tags` — all tags with numbers of tagged files,
+//!   `[{"tag": "cats", "count": 3}]`
+//! * `GET /query?tag=cats&tag=dogs` — indexed files having all the tags,
+//!   `[{"path": "/photos/a.jpg", "id": "s1024_h3735928559", "mime": "image/jpeg"}]`
+//! * `GET /tags-of?path=a.jpg` or `?id=s1024_h3735928559` — `["cats"]`
+//! * `GET /file?path=a.jpg` or `?id=...` — content of the file,
+//!   with its sniffed type as `Content-Type`; only regular files
+//!   of the root are served, links leading outside of it are not
+//! * `POST /tag`, `POST /untag` with `{"tag": "cats", "paths": [...], "ids": [...]}`,
+//!   both lists are optional; rejected in read-only mode
+//!
+//! Paths are absolute or relative to the root. Failures are reported
+//! with a status code and `{"error": "..."}`.
+
+use crate::model::{Model, id::Id, tag::Tag};
+use crate::xattrs;
+use crate::mime;
+use crate::error::{Error, Result};
+
+use serde::{Serialize, Deserialize};
+use tiny_http::{Method, Header, StatusCode};
+
+use std::fs::{self, File};
+use std::io::Cursor;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+type Response = tiny_http::Response<Box<dyn std::io::Read + Send>>;
+
+pub struct Server {
+    server: tiny_http::Server,
+    model: Model,
+    token: String,
+    read_only: bool,
+}
+
+#[derive(Serialize)]
+struct TagCount<'a> {
+    tag: &'a Tag,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    path: PathBuf,
+    id: String,
+    mime: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Tagging {
+    tag: Tag,
+    #[serde(default)]
+    paths: Vec<PathBuf>,
+    #[serde(default)]
+    ids: Vec<String>,
+}
+
+//reported to the client, the status code is derived from the variant
+enum Failure {
+    BadRequest(String),
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    Internal(Error),
+}
+
+impl From<Error> for Failure {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::OutsideRoot { .. } | Error::EmptyTag | Error::TagTooLong { .. } =>
+                Failure::BadRequest(error.to_string()),
+            error => Failure::Internal(error)
+        }
+    }
+}
+
+impl Server {
+    /// Binds to `127.0.0.1:port`, port 0 picks any free port.
+    pub fn bind(model: Model, port: u16, token: String, read_only: bool) -> Result<Self> {
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let server = tiny_http::Server::http(address)
+            .map_err(|error| Error::Http { message: error.to_string() })?;
+
+        Ok(Server { server, model, token, read_only })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.server.server_addr()
+    }
+
+    /// Serves requests one by one until the process is killed.
+    pub fn run(mut self) {
+        while let Ok(mut request) = self.server.recv() {
+            crate::trace!("\t{} {}", request.method(), request.url());
+
+            let response = self.handle(&mut request)
+                .unwrap_or_else(|failure| failure.response());
+
+            if let Err(error) = request.respond(response) {
+                println!("WARNING: {}", error);
+            }
+        }
+    }
+
+    fn handle(&mut self, request: &mut tiny_http::Request) -> std::result::Result<Response, Failure> {
+        if !self.authorized(request) {
+            return Err(Failure::Unauthorized);
+        }
+
+        let url = request.url().to_owned();
+        let (route, query) = match url.find('?') {
+            Some(i) => (&url[..i], &url[i + 1..]),
+            None => (url.as_str(), "")
+        };
+        let parameters: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+
+        match (request.method(), route) {
+            (Method::Get, "/tags") => {
+                let counts: Vec<TagCount> = self.model.database.counts()
+                    .map(|(tag, count)| TagCount { tag, count })
+                    .collect();
+                json(&counts)
+            },
+            (Method::Get, "/query") => {
+                let tags: Vec<Tag> = values(&parameters, "tag").cloned().collect();
+                let entries: Vec<Entry> = self.model.query(&tags).into_iter()
+                    .filter_map(|path| {
+                        let id = self.model.index.id(&path)?;
+                        let mime = self.model.index.mime(&path).map(String::from);
+                        Some(Entry { path, id: id.to_string(), mime })
+                    })
+                    .collect();
+                json(&entries)
+            },
+            (Method::Get, "/tags-of") => {
+                let tags: Vec<&Tag> = match self.target(&parameters)? {
+                    (_, Some(id)) => self.model.database.tags_of(id).collect(),
+                    (_, None) => vec![]
+                };
+                json(&tags)
+            },
+            (Method::Get, "/file") => {
+                let (path, id) = self.target(&parameters)?;
+                if id.is_none() {
+                    return Err(Failure::NotFound);
+                }
+                let file = self.open(&path)?;
+                let length = file.metadata().ok().map(|meta| meta.len() as usize);
+                let content_type = self.model.index.mime(&path).unwrap_or(mime::UNKNOWN);
+                Ok(response(Box::new(file), length, 200, content_type))
+            },
+            (Method::Post, "/tag") | (Method::Post, "/untag") => {
+                if self.read_only {
+                    return Err(Failure::Forbidden);
+                }
+
+                let tagging: Tagging = serde_json::from_reader(request.as_reader())
+                    .map_err(|error| Failure::BadRequest(error.to_string()))?;
+                if route == "/tag" && tagging.tag.is_empty() {
+                    return Err(Error::EmptyTag.into());
+                }
+                let ids = self.ids(&tagging)?;
+
+                if route == "/tag" {
+                    self.model.database.insert(ids.into_iter(), &tagging.tag)?;
+                } else {
+                    self.model.database.remove(ids.into_iter(), &tagging.tag)?;
+                }
+                self.save();
+                json(&())
+            },
+            (_, "/tags") | (_, "/query") | (_, "/tags-of") | (_, "/file") |
+            (_, "/tag") | (_, "/untag") => Err(Failure::MethodNotAllowed),
+            _ => Err(Failure::NotFound)
+        }
+    }
+
+    //compared in constant time, so the token can't be guessed byte by byte
+    fn authorized(&self, request: &tiny_http::Request) -> bool {
+        let expected = format!("Bearer {}", self.token);
+
+        request.headers().iter()
+            .filter(|header| header.field.equiv("Authorization"))
+            .any(|header| {
+                let actual = header.value.as_str().as_bytes();
+                actual.len() == expected.len() &&
+                    actual.iter().zip(expected.as_bytes())
+                        .fold(0, |acc, (a, b)| acc | (a ^ b)) == 0
+            })
+    }
+
+    //a file is given either with `path` or with `id`
+    fn target(&mut self, parameters: &[(String, String)]) -> std::result::Result<(PathBuf, Option<Id>), Failure> {
+        if let Some(path) = values(parameters, "path").next() {
+            let path = self.model.resolve(path.as_ref())?;
+            let id = self.model.id_of(&path).map_err(|_| Failure::NotFound)?;
+            return Ok((path, id));
+        }
+
+        if let Some(id) = values(parameters, "id").next() {
+            let id = parse_id(id)?;
+            let path = self.model.path_of(id).ok_or(Failure::NotFound)?;
+            return Ok((path, Some(id)));
+        }
+
+        Err(Failure::BadRequest("either `path` or `id` is required".to_owned()))
+    }
+
+    //links are resolved, the target must be a regular file of the root;
+    //opening a pipe would block the server
+    fn open(&self, path: &Path) -> std::result::Result<File, Failure> {
+        let path = path.canonicalize().map_err(|_| Failure::NotFound)?;
+        if !path.starts_with(&self.model.root) {
+            return Err(Failure::NotFound);
+        }
+
+        let regular = |meta: fs::Metadata| meta.is_file();
+        if !fs::metadata(&path).map(regular).unwrap_or(false) {
+            return Err(Failure::NotFound);
+        }
+        //the file could be replaced in between
+        let file = File::open(&path).map_err(|_| Failure::NotFound)?;
+        if !file.metadata().map(regular).unwrap_or(false) {
+            return Err(Failure::NotFound);
+        }
+        Ok(file)
+    }
+
+    fn ids(&mut self, tagging: &Tagging) -> std::result::Result<Vec<Id>, Failure> {
+        let mut ids = vec![];
+        for path in tagging.paths.iter() {
+            let path = self.model.resolve(path)?;
+            if let Some(id) = self.model.id_of(&path)? {
+                ids.push(id);
+            }
+        }
+        for id in tagging.ids.iter() {
+            ids.push(parse_id(id)?);
+        }
+        Ok(ids)
+    }
+
+    fn save(&mut self) {
+        if let Err(error) = self.model.index.save() {
+            println!("WARNING: {}", error);
+        }
+        for error in self.model.database.take_errors() {
+            println!("WARNING: {}", error);
+        }
+        for error in xattrs::write_through(&mut self.model) {
+            println!("WARNING: {}", error);
+        }
+    }
+}
+
+impl Failure {
+    fn response(self) -> Response {
+        let (status, message) = match self {
+            Failure::BadRequest(message) => (400, message),
+            Failure::Unauthorized => (401, "missing or wrong token".to_owned()),
+            Failure::Forbidden => (403, "the server is read-only".to_owned()),
+            Failure::NotFound => (404, "not found".to_owned()),
+            Failure::MethodNotAllowed => (405, "method not allowed".to_owned()),
+            Failure::Internal(error) => (500, error.to_string()),
+        };
+
+        let body = serde_json::json!({ "error": message }).to_string().into_bytes();
+        let length = body.len();
+        response(Box::new(Cursor::new(body)), Some(length), status, "application/json")
+    }
+}
+
+fn values<'a>(parameters: &'a [(String, String)], name: &'a str) -> impl Iterator<Item = &'a String> {
+    parameters.iter()
+        .filter(move |(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+fn parse_id(id: &str) -> std::result::Result<Id, Failure> {
+    id.parse()
+        .map_err(|error| Failure::BadRequest(format!("{}: {:?}", id, error)))
+}
+
+fn json<T: Serialize>(value: &T) -> std::result::Result<Response, Failure> {
+    let body = serde_json::to_vec(value)
+        .map_err(|error| Failure::BadRequest(error.to_string()))?;
+    let length = body.len();
+    Ok(response(Box::new(Cursor::new(body)), Some(length), 200, "application/json"))
+}
+
+fn response(data: Box<dyn std::io::Read + Send>, length: Option<usize>,
+            status: u16, content_type: &str) -> Response {
+    let header = Header::from_bytes(&b"Content-Type"[..], content_type.as_bytes())
+        .unwrap();
+
+    tiny_http::Response::new(StatusCode(status), vec![header], data, length, None)
+}
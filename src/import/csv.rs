@@ -0,0 +1,45 @@
+//! Tags listed in CSV files as `path,tag1;tag2`, a header row
+//! starting with `path` is skipped.
+
+use super::Rules;
+use crate::model::tag::Tag;
+use crate::error::{Error, Result};
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+const SEPARATOR: char = ';';
+
+/// Tagged paths of `file`, in order of rows.
+pub fn read(file: &Path, rules: &Rules) -> Result<Vec<(PathBuf, BTreeSet<Tag>)>> {
+    let error = |error: csv::Error| Error::Import {
+        path: file.to_path_buf(), message: error.to_string()
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(file)
+        .map_err(error)?;
+
+    let mut listed = vec![];
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(error)?;
+        let path = match record.get(0) {
+            Some(path) if !path.trim().is_empty() => path.trim(),
+            _ => continue
+        };
+        if i == 0 && path.eq_ignore_ascii_case("path") {
+            continue;
+        }
+
+        //unquoted commas split tags into more fields
+        let tags = record.iter()
+            .skip(1)
+            .flat_map(|field| field.split(SEPARATOR))
+            .filter_map(|keyword| rules.tag(keyword))
+            .collect();
+        listed.push((PathBuf::from(path), tags));
+    }
+    Ok(listed)
+}
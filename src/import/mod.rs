@@ -0,0 +1,215 @@
+//! Importers of tags found in metadata of files and in other tag databases.
+//!
+//! An import is planned first, so the proposed tags can be previewed,
+//! and applied to the database afterwards. Keywords are turned into
+//! tags by `Rules` from the configuration.
+//!
+//! Importers may own namespaces, e.g. `artist:`: tags in them which
+//! the metadata doesn't have anymore are removed when re-importing.
+//! Editing metadata changes content, so tags of the previous content
+//! are moved to the file when the import is applied.
+
+pub mod photo;
+pub mod music;
+pub mod sidecars;
+pub mod tmsu;
+pub mod csv;
+
+use crate::model::{Model, id::Id, tag::Tag};
+use crate::database::Database;
+use crate::index::Change;
+use crate::config;
+use crate::error::{Error, Result};
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Normalization and aliases of imported keywords.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    normalize: bool,
+    aliases: HashMap<String, Tag>,
+}
+
+/// Tags which a file doesn't have yet, and tags of owned
+/// namespaces which it must not have anymore.
+#[derive(Debug, Clone)]
+pub struct Proposal {
+    pub path: PathBuf,
+    pub id: Id,
+    //id of the content before it was changed
+    pub previous: Option<Id>,
+    pub tags: BTreeSet<Tag>,
+    pub stale: BTreeSet<Tag>,
+}
+
+#[derive(Debug, Default)]
+pub struct Plan {
+    pub proposals: Vec<Proposal>,
+    //files which metadata or ids couldn't be read
+    pub errors: Vec<Error>,
+    //listed paths which are not files of the root
+    pub unresolved: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub tagged: usize,
+    pub created: Vec<Tag>,
+    pub removed: Vec<Tag>,
+}
+
+impl Rules {
+    pub fn new(config: &config::Import) -> Self {
+        let mut rules = Rules { normalize: config.normalize, aliases: HashMap::new() };
+        rules.aliases = config.aliases.iter()
+            .map(|(alias, tag)| (rules.normalized(alias), tag.clone()))
+            .collect();
+        rules
+    }
+
+    /// Tag for a keyword, `None` if nothing is left of it.
+    pub fn tag(&self, keyword: &str) -> Option<Tag> {
+        let keyword = self.normalized(keyword);
+        if keyword.is_empty() {
+            return None;
+        }
+
+        Some(self.aliases.get(&keyword).cloned().unwrap_or(keyword))
+    }
+
+    fn normalized(&self, keyword: &str) -> String {
+        if self.normalize {
+            keyword.split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ")
+                .to_lowercase()
+        } else {
+            keyword.trim().to_owned()
+        }
+    }
+}
+
+impl Plan {
+    /// Reads tags of indexed regular files with `read`, which returns `None`
+    /// for files it doesn't handle; the whole tree must be scanned
+    /// and `changes` are the changes found by the scan.
+    pub fn collect<F>(model: &Model, changes: &[Change], namespaces: &[&str], mut read: F) -> Self
+        where F: FnMut(&Path) -> Result<Option<BTreeSet<Tag>>> {
+        let mut entries: Vec<(&PathBuf, &Id)> = model.index.entries()
+            .filter(|(path, _)| fs::metadata(path).map(|meta| meta.is_file()).unwrap_or(false))
+            .collect();
+        entries.sort();
+
+        let previous: HashMap<&PathBuf, Id> = changes.iter()
+            .map(|change| (&change.path, change.old))
+            .collect();
+
+        let mut plan = Plan::default();
+        for (path, id) in entries {
+            match read(path) {
+                Ok(Some(tags)) => {
+                    let previous = previous.get(path).copied();
+                    plan.propose(&model.database, path.clone(), *id, previous, tags, namespaces);
+                },
+                Ok(None) => {},
+                Err(error) => plan.errors.push(error)
+            }
+        }
+        plan
+    }
+
+    /// Plans tags listed by path, relative paths are relative to `base`;
+    /// paths listed several times get all their tags.
+    pub fn resolve<I>(model: &mut Model, base: &Path, listed: I) -> Self
+        where I: IntoIterator<Item = (PathBuf, BTreeSet<Tag>)> {
+        let mut tags_by_path: BTreeMap<PathBuf, BTreeSet<Tag>> = BTreeMap::new();
+        for (path, tags) in listed {
+            //`dir/./file` is `dir/file`
+            let path = base.join(path).components().collect();
+            tags_by_path.entry(path).or_default().extend(tags);
+        }
+
+        let mut plan = Plan::default();
+        for (path, tags) in tags_by_path {
+            let id = model.resolve(&path)
+                .and_then(|path| model.id_of(&path));
+
+            match id {
+                Ok(Some(id)) => plan.propose(&model.database, path, id, None, tags, &[]),
+                //directories, missing files and files of other roots
+                Ok(None) | Err(Error::OutsideRoot { .. }) => plan.unresolved.push(path),
+                Err(Error::Io { ref source, .. }) if source.kind() == ErrorKind::NotFound =>
+                    plan.unresolved.push(path),
+                Err(error) => plan.errors.push(error)
+            }
+        }
+        plan
+    }
+
+    //tags which the file has already are left out
+    pub fn propose(&mut self, database: &Database, path: PathBuf, id: Id, previous: Option<Id>,
+                   mut tags: BTreeSet<Tag>, namespaces: &[&str]) {
+        let current: BTreeSet<&Tag> = database.tags_of(id)
+            .chain(previous.into_iter().flat_map(|previous| database.tags_of(previous)))
+            .collect();
+
+        let mut stale = BTreeSet::new();
+        for tag in current {
+            let owned = namespaces.iter()
+                .any(|namespace| tag.starts_with(namespace));
+            if !tags.remove(tag) && owned {
+                stale.insert(tag.clone());
+            }
+        }
+
+        let changed = previous.map(|previous| database.tags_of(previous).next().is_some())
+            .unwrap_or(false);
+        if !tags.is_empty() || !stale.is_empty() || changed {
+            self.proposals.push(Proposal { path, id, previous, tags, stale });
+        }
+    }
+
+    /// Tags which nothing is tagged with yet.
+    pub fn created(&self, database: &Database) -> BTreeSet<&Tag> {
+        self.proposals.iter()
+            .flat_map(|proposal| proposal.tags.iter())
+            .filter(|tag| !database.contains(tag))
+            .collect()
+    }
+
+    pub fn apply(self, database: &mut Database) -> Result<Report> {
+        let mut added: BTreeMap<&Tag, Vec<Id>> = BTreeMap::new();
+        let mut stale: BTreeMap<&Tag, Vec<Id>> = BTreeMap::new();
+        for proposal in self.proposals.iter() {
+            for tag in proposal.tags.iter() {
+                added.entry(tag).or_default().push(proposal.id);
+            }
+            for tag in proposal.stale.iter() {
+                stale.entry(tag).or_default().push(proposal.id);
+            }
+        }
+
+        //the previous content is kept tagged, it can be another file's content
+        for proposal in self.proposals.iter() {
+            if let Some(previous) = proposal.previous {
+                database.migrate(previous, proposal.id, true)?;
+            }
+        }
+
+        let mut report = Report { tagged: self.proposals.len(), ..Report::default() };
+        for (tag, ids) in stale {
+            if database.remove(ids.into_iter(), tag)? {
+                report.removed.push(tag.clone());
+            }
+        }
+        for (tag, ids) in added {
+            if database.insert(ids.into_iter(), tag)? {
+                report.created.push(tag.clone());
+            }
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,231 @@
+//! Artist, album, genre and year of audio files as namespaced tags:
+//! `artist:...`, `album:...`, `genre:...` and `year:...`.
+//!
+//! ID3v2 is read from MP3, Vorbis comments from FLAC and Ogg (Vorbis and Opus).
+
+use super::Rules;
+use crate::model::tag::Tag;
+use crate::error::{Error, Result, WithPath};
+
+use id3::TagLike;
+
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+
+pub const NAMESPACES: &[&str] = &["artist:", "album:", "genre:", "year:"];
+
+const FLAC: &[u8] = b"fLaC";
+const VORBIS_COMMENT: u8 = 4;
+
+const OGG: &[u8] = b"OggS";
+const VORBIS_COMMENTS: &[u8] = b"\x03vorbis";
+const OPUS_COMMENTS: &[u8] = b"OpusTags";
+
+#[derive(Debug, Default)]
+struct Metadata {
+    artists: Vec<String>,
+    albums: Vec<String>,
+    genres: Vec<String>,
+    year: Option<i32>,
+}
+
+/// Tags of an audio file, `None` if the format is not supported.
+pub fn read(path: &Path, rules: &Rules) -> Result<Option<BTreeSet<Tag>>> {
+    let extension = path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let metadata = match extension.as_str() {
+        "mp3" => id3(path)?,
+        "flac" => {
+            let mut file = open(path)?;
+            comments(flac(&mut file).with_path(path)?)
+        },
+        "ogg" | "oga" | "opus" => {
+            let mut file = open(path)?;
+            comments(ogg(&mut file).with_path(path)?)
+        },
+        _ => return Ok(None)
+    };
+
+    let mut tags = BTreeSet::new();
+    let values = [
+        ("artist:", &metadata.artists),
+        ("album:", &metadata.albums),
+        ("genre:", &metadata.genres),
+    ];
+    for (namespace, values) in values.iter() {
+        for value in values.iter() {
+            if let Some(value) = rules.tag(value) {
+                tags.insert(format!("{}{}", namespace, value));
+            }
+        }
+    }
+    if let Some(year) = metadata.year {
+        tags.insert(format!("year:{}", year));
+    }
+    Ok(Some(tags))
+}
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .with_path(path)
+}
+
+//files without a tag have no metadata
+fn id3(path: &Path) -> Result<Metadata> {
+    let tag = match id3::Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(error) => match error.kind {
+            id3::ErrorKind::NoTag => return Ok(Metadata::default()),
+            _ => {
+                let message = error.to_string();
+                return Err(Error::Metadata { path: path.to_path_buf(), message });
+            }
+        }
+    };
+
+    let owned = |values: Option<Vec<&str>>| values.unwrap_or_default()
+        .into_iter()
+        .map(|value| value.to_owned())
+        .collect();
+
+    Ok(Metadata {
+        artists: owned(tag.artists()),
+        albums: owned(tag.album().map(|album| vec![album])),
+        genres: tag.genre_parsed()
+            .map(|genre| vec![genre.into_owned()])
+            .unwrap_or_default(),
+        year: tag.year().or_else(|| tag.date_recorded().map(|date| date.year)),
+    })
+}
+
+//`KEY=value` pairs, keys are case-insensitive and may repeat
+fn comments(pairs: Vec<(String, String)>) -> Metadata {
+    let mut metadata = Metadata::default();
+
+    for (key, value) in pairs {
+        match key.to_uppercase().as_str() {
+            "ARTIST" => metadata.artists.push(value),
+            "ALBUM" => metadata.albums.push(value),
+            "GENRE" => metadata.genres.push(value),
+            //"2019-05-01" or "2019"
+            "DATE" => if metadata.year.is_none() {
+                metadata.year = value.get(..4).and_then(|year| year.parse().ok());
+            },
+            _ => {}
+        }
+    }
+    metadata
+}
+
+//metadata blocks precede the audio frames:
+//a header with the last-block flag, the type and the length, then the data
+fn flac<R: Read>(reader: &mut R) -> std::io::Result<Vec<(String, String)>> {
+    let mut signature = [0; 4];
+    reader.read_exact(&mut signature)?;
+    if signature != FLAC {
+        return Err(malformed("not a FLAC stream"));
+    }
+
+    loop {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        let last = header[0] & 0x80 != 0;
+        let kind = header[0] & 0x7F;
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+
+        let mut data = vec![0; length];
+        reader.read_exact(&mut data)?;
+        if kind == VORBIS_COMMENT {
+            return vorbis_comments(&data);
+        }
+        if last {
+            return Ok(vec![]);
+        }
+    }
+}
+
+//comments are the second packet of the first logical stream,
+//packets are split into segments of up to 255 bytes spread over pages
+fn ogg<R: Read>(reader: &mut R) -> std::io::Result<Vec<(String, String)>> {
+    let mut packets: Vec<Vec<u8>> = vec![vec![]];
+    let mut serial = None;
+
+    while packets.len() < 3 {
+        let mut header = [0; 27];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != OGG {
+            return Err(malformed("not an Ogg stream"));
+        }
+
+        let mut table = vec![0; header[26] as usize];
+        reader.read_exact(&mut table)?;
+        let size = table.iter().map(|segment| *segment as usize).sum();
+        let mut data = vec![0; size];
+        reader.read_exact(&mut data)?;
+
+        let current = u32::from_le_bytes([header[14], header[15], header[16], header[17]]);
+        if *serial.get_or_insert(current) != current {
+            continue;
+        }
+
+        let mut offset = 0;
+        for segment in table {
+            let end = offset + segment as usize;
+            packets.last_mut().unwrap().extend_from_slice(&data[offset..end]);
+            offset = end;
+            if segment < 255 {
+                packets.push(vec![]);
+            }
+        }
+    }
+
+    let packet = &packets[1];
+    if packet.starts_with(VORBIS_COMMENTS) {
+        vorbis_comments(&packet[VORBIS_COMMENTS.len()..])
+    } else if packet.starts_with(OPUS_COMMENTS) {
+        vorbis_comments(&packet[OPUS_COMMENTS.len()..])
+    } else {
+        Ok(vec![])
+    }
+}
+
+//little-endian lengths: the vendor, the number of comments, every comment
+fn vorbis_comments(data: &[u8]) -> std::io::Result<Vec<(String, String)>> {
+    let mut data = data;
+    let vendor = length(&mut data)?;
+    skip(&mut data, vendor)?;
+
+    let count = length(&mut data)?;
+    let mut pairs = vec![];
+    for _ in 0..count {
+        let size = length(&mut data)?;
+        let comment = String::from_utf8_lossy(skip(&mut data, size)?);
+        if let Some(i) = comment.find('=') {
+            pairs.push((comment[..i].to_owned(), comment[i + 1..].to_owned()));
+        }
+    }
+    Ok(pairs)
+}
+
+fn length(data: &mut &[u8]) -> std::io::Result<usize> {
+    let bytes = skip(data, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+fn skip<'a>(data: &mut &'a [u8], size: usize) -> std::io::Result<&'a [u8]> {
+    if data.len() < size {
+        return Err(malformed("truncated comments"));
+    }
+    let (head, tail) = data.split_at(size);
+    *data = tail;
+    Ok(head)
+}
+
+fn malformed(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,181 @@
+//! Keywords of photos, from IPTC and XMP, and optionally tags derived
+//! from EXIF: `year/2019`, `camera/x100f` and `geotagged`. Derived tags
+//! are normalized and aliased like keywords.
+
+use super::Rules;
+use crate::model::tag::Tag;
+use crate::xmp;
+use crate::error::{Error, Result, WithPath};
+
+use exif::In;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+const EXTENSIONS: &[&str] = &["jpg", "jpeg", "tif", "tiff", "png", "heic", "heif", "webp", "dng"];
+
+//markers of JPEG segments
+const START_OF_SCAN: u8 = 0xDA;
+const APP13: u8 = 0xED;
+
+const PHOTOSHOP: &[u8] = b"Photoshop 3.0\0";
+const RESOURCE: &[u8] = b"8BIM";
+const IPTC_NAA: u16 = 0x0404;
+
+/// Tags of a photo, `None` if the file is not a photo.
+pub fn read(path: &Path, rules: &Rules, derived: bool) -> Result<Option<BTreeSet<Tag>>> {
+    let photo = path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .map(|extension| EXTENSIONS.contains(&extension.as_str()))
+        .unwrap_or(false);
+    if !photo {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(path).with_path(path)?;
+    let mut keywords = iptc_keywords(&bytes);
+
+    if let Some(packet) = xmp::packet(&bytes) {
+        let subjects = std::str::from_utf8(packet)
+            .map_err(|error| error.to_string())
+            .and_then(|xml| xmp::subjects(xml).map_err(|error| error.to_string()))
+            .map_err(|message| Error::Metadata { path: path.to_path_buf(), message })?;
+        keywords.extend(subjects);
+    }
+
+    if derived {
+        keywords.extend(derive(&bytes));
+    }
+
+    let tags = keywords.iter()
+        .filter_map(|keyword| rules.tag(keyword))
+        .collect();
+    Ok(Some(tags))
+}
+
+//photos without EXIF are fine, so are unknown formats
+fn derive(bytes: &[u8]) -> Vec<Tag> {
+    let exif = match exif::Reader::new().read_from_container(&mut Cursor::new(bytes)) {
+        Ok(exif) => exif,
+        Err(_) => return vec![]
+    };
+    let text = |tag: exif::Tag| match exif.get_field(tag, In::PRIMARY).map(|field| &field.value) {
+        Some(exif::Value::Ascii(values)) => values.first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_owned())
+            .filter(|value| !value.is_empty()),
+        _ => None
+    };
+
+    let mut tags = vec![];
+
+    //"2019:05:01 12:00:00"
+    let date = text(exif::Tag::DateTimeOriginal).or_else(|| text(exif::Tag::DateTime));
+    if let Some(year) = date.as_ref().and_then(|date| date.get(..4)) {
+        if year.chars().all(|c| c.is_ascii_digit()) && year != "0000" {
+            tags.push(format!("year/{}", year));
+        }
+    }
+
+    if let Some(model) = text(exif::Tag::Model) {
+        tags.push(format!("camera/{}", model));
+    }
+
+    if exif.get_field(exif::Tag::GPSLatitude, In::PRIMARY).is_some() {
+        tags.push("geotagged".to_owned());
+    }
+    tags
+}
+
+//IPTC is read only from JPEG, where it's a resource of the Photoshop segment
+fn iptc_keywords(bytes: &[u8]) -> Vec<String> {
+    segments(bytes).into_iter()
+        .filter(|(marker, data)| *marker == APP13 && data.starts_with(PHOTOSHOP))
+        .filter_map(|(_, data)| resource(&data[PHOTOSHOP.len()..], IPTC_NAA))
+        .flat_map(datasets)
+        .collect()
+}
+
+//segments of a JPEG before the compressed data
+fn segments(bytes: &[u8]) -> Vec<(u8, &[u8])> {
+    let mut segments = vec![];
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return segments;
+    }
+
+    let mut i = 2;
+    while i + 4 <= bytes.len() && bytes[i] == 0xFF {
+        let marker = bytes[i + 1];
+        if marker == 0xFF {
+            //fill byte
+            i += 1;
+            continue;
+        }
+        if marker == START_OF_SCAN {
+            break;
+        }
+
+        let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+        let end = i + 2 + length;
+        if length < 2 || end > bytes.len() {
+            break;
+        }
+
+        segments.push((marker, &bytes[i + 4..end]));
+        i = end;
+    }
+    segments
+}
+
+//image resource blocks: signature, id, padded pascal name, size, padded data
+fn resource(mut data: &[u8], id: u16) -> Option<&[u8]> {
+    while data.len() >= 12 && data.starts_with(RESOURCE) {
+        let current = u16::from_be_bytes([data[4], data[5]]);
+        let name = 1 + data[6] as usize;
+        let i = 6 + name + name % 2;
+        if i + 4 > data.len() {
+            break;
+        }
+
+        let size = u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]) as usize;
+        let start = i + 4;
+        let end = start + size;
+        if end > data.len() {
+            break;
+        }
+
+        if current == id {
+            return Some(&data[start..end]);
+        }
+        data = &data[(end + size % 2).min(data.len())..];
+    }
+    None
+}
+
+//keywords are datasets 2:25 of IPTC-NAA, assumed to be UTF-8
+fn datasets(iptc: &[u8]) -> Vec<String> {
+    let mut keywords = vec![];
+
+    let mut i = 0;
+    while i + 5 <= iptc.len() && iptc[i] == 0x1C {
+        let (record, dataset) = (iptc[i + 1], iptc[i + 2]);
+        let size = u16::from_be_bytes([iptc[i + 3], iptc[i + 4]]) as usize;
+        //extended datasets are never keywords
+        if size & 0x8000 != 0 {
+            break;
+        }
+
+        let start = i + 5;
+        let end = start + size;
+        if end > iptc.len() {
+            break;
+        }
+
+        if record == 2 && dataset == 25 {
+            keywords.push(String::from_utf8_lossy(&iptc[start..end]).into_owned());
+        }
+        i = end;
+    }
+    keywords
+}
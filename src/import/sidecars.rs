@@ -0,0 +1,17 @@
+//! Subjects of XMP sidecars written by other tools or exported earlier.
+
+use super::Rules;
+use crate::model::tag::Tag;
+use crate::sidecar;
+use crate::error::Result;
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+/// Tags of the sidecar of `file`, `None` if there is no sidecar.
+pub fn read(file: &Path, rules: &Rules) -> Result<Option<BTreeSet<Tag>>> {
+    let subjects = sidecar::read(file)?;
+    Ok(subjects.map(|subjects| subjects.iter()
+        .filter_map(|subject| rules.tag(subject))
+        .collect()))
+}
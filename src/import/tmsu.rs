@@ -0,0 +1,65 @@
+//! Tags of a TMSU database, `.tmsu/db`. Tags with a value, `year=2019`,
+//! are imported as `year=2019`.
+
+use super::Rules;
+use crate::model::tag::Tag;
+use crate::error::{Error, Result};
+
+use rusqlite::{Connection, OpenFlags, NO_PARAMS};
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+const DIRECTORY: &str = ".tmsu";
+
+const QUERY: &str = "
+    SELECT file.directory, file.name, tag.name, value.name
+    FROM file_tag
+    JOIN file ON file.id = file_tag.file_id
+    JOIN tag ON tag.id = file_tag.tag_id
+    LEFT JOIN value ON value.id = file_tag.value_id
+    ORDER BY file.directory, file.name";
+
+/// Directory which relative paths of `database` are relative to:
+/// TMSU stores them relative to the parent of `.tmsu`.
+pub fn base(database: &Path, root: &Path) -> PathBuf {
+    database.parent()
+        .filter(|directory| directory.file_name()
+            .map(|name| name == DIRECTORY)
+            .unwrap_or(false))
+        .and_then(|directory| directory.parent())
+        .map(|base| base.to_path_buf())
+        .unwrap_or_else(|| root.to_path_buf())
+}
+
+/// Tagged paths of `database`, a path is listed once per tag.
+pub fn read(database: &Path, rules: &Rules) -> Result<Vec<(PathBuf, BTreeSet<Tag>)>> {
+    let error = |error: rusqlite::Error| Error::Import {
+        path: database.to_path_buf(), message: error.to_string()
+    };
+
+    let connection = Connection::open_with_flags(database, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(error)?;
+    let mut statement = connection.prepare(QUERY).map_err(error)?;
+    let rows = statement.query_map(NO_PARAMS, |row| Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, Option<String>>(3)?)))
+        .map_err(error)?;
+
+    let mut listed = vec![];
+    for row in rows {
+        let (directory, name, tag, value) = row.map_err(error)?;
+        let keyword = match value {
+            Some(value) if !value.is_empty() => format!("{}={}", tag, value),
+            _ => tag
+        };
+
+        if let Some(tag) = rules.tag(&keyword) {
+            let path = Path::new(&directory).join(name);
+            listed.push((path, std::iter::once(tag).collect()));
+        }
+    }
+    Ok(listed)
+}
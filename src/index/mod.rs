@@ -0,0 +1,428 @@
+mod storage;
+pub mod verify;
+
+use crate::file;
+use crate::mime;
+use crate::model::id::Id;
+use crate::model::ignores::Ignores;
+use crate::model::entry::{FileKind, SymlinkPolicy};
+use crate::error::{Error, Result, WithPath};
+use crate::utils::measure;
+
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet, BTreeSet};
+use std::os::unix::fs::MetadataExt;
+use std::time::UNIX_EPOCH;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+/// Ids of files of one root, persisted in the root.
+#[derive(Debug, Clone)]
+pub struct Index {
+    path: PathBuf,
+    id_by_path: HashMap<PathBuf, Id>,
+    //files with the same content share the id
+    paths_by_id: HashMap<Id, BTreeSet<PathBuf>>,
+    //persisted between runs, files with the same size and
+    //modification time as recorded are not re-hashed
+    records: HashMap<PathBuf, Record>,
+    hashing: Hashing,
+    changes: Vec<Change>,
+    //ids changed by hashing again, not by content changes
+    rehashed: Vec<Change>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub id: Id,
+    pub kind: FileKind,
+    pub size: u64,
+    //nanoseconds since unix epoch
+    pub modified: u64,
+    //sniffed together with hashing, empty if unknown yet
+    pub mime: String,
+    //computed by an older version of hashing, so the file is hashed again
+    pub outdated: bool
+}
+
+//content of a known path has been changed,
+//so tags of the old id can be moved to the new one
+#[derive(Debug, Clone)]
+pub struct Change {
+    pub path: PathBuf,
+    pub old: Id,
+    pub new: Id
+}
+
+//whether persisted records can be trusted instead of re-hashing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Hashing {
+    Cached,
+    Always
+}
+
+impl Default for Hashing {
+    fn default() -> Self {
+        Hashing::Cached
+    }
+}
+
+impl FromStr for Hashing {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "cached" => Ok(Hashing::Cached),
+            "always" => Ok(Hashing::Always),
+            _ => Err(format!("unknown hashing mode: {}", s))
+        }
+    }
+}
+
+//what to do with tags of files which content has changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationPolicy {
+    Apply,
+    Ask,
+    Ignore
+}
+
+impl Default for MigrationPolicy {
+    fn default() -> Self {
+        MigrationPolicy::Ask
+    }
+}
+
+impl FromStr for MigrationPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "apply" => Ok(MigrationPolicy::Apply),
+            "ask" => Ok(MigrationPolicy::Ask),
+            "ignore" => Ok(MigrationPolicy::Ignore),
+            _ => Err(format!("unknown migration policy: {}", s))
+        }
+    }
+}
+
+impl Index {
+    pub fn new(path: PathBuf) -> Index {
+        Index {
+            path,
+            id_by_path: HashMap::new(),
+            paths_by_id: HashMap::new(),
+            records: HashMap::new(),
+            hashing: Hashing::default(),
+            changes: vec![],
+            rehashed: vec![]
+        }
+    }
+
+    //records of the previous runs are loaded, if there are any
+    pub fn load(path: PathBuf) -> Result<Index> {
+        let records = storage::load(&path)?;
+        Ok(Index { records, ..Index::new(path) })
+    }
+
+    pub fn with_hashing(self, hashing: Hashing) -> Self {
+        Index { hashing, ..self }
+    }
+
+    pub fn hashing(&self) -> Hashing {
+        self.hashing
+    }
+
+    pub fn save(&self) -> Result<()> {
+        storage::save(&self.path, &self.records)
+    }
+
+    /// Computes the id of `path` unless it is known and unchanged.
+    pub fn provide(&mut self, path: &Path, kind: FileKind) -> Result<()> {
+        let (size, modified) = match stamp(path, kind)? {
+            Some(stamp) => stamp,
+            None => return Ok(())
+        };
+
+        let recorded = self.records.get(path).cloned();
+        let cached = recorded.as_ref()
+            .filter(|_| self.hashing == Hashing::Cached)
+            .filter(|record| !record.outdated && record.kind == kind &&
+                record.size == size && record.modified == modified)
+            .map(|record| (record.id, record.mime.clone()));
+
+        let (id, mime) = match cached {
+            Some((id, mime)) if !mime.is_empty() => (id, mime),
+            //records of older indexes have no MIME type
+            Some((id, _)) => (id, sniff(path, kind)?),
+            None => (identify(path, kind)?, sniff(path, kind)?)
+        };
+
+        self.records.insert(path.to_path_buf(), Record { id, kind, size, modified, mime, outdated: false });
+
+        let live = measure("index.id.insertion", ||
+            self.id_by_path.insert(path.to_path_buf(), id));
+        if let Some(live) = live {
+            self.unlink(live, path);
+        }
+        self.paths_by_id.entry(id).or_default().insert(path.to_path_buf());
+
+        let outdated = live.is_none() &&
+            recorded.as_ref().map(|record| record.outdated).unwrap_or(false);
+        let old = live.or(recorded.map(|record| record.id));
+
+        if let Some(old) = old {
+            if old != id && outdated {
+                self.rehashed.push(Change { path: path.to_path_buf(), old, new: id });
+            } else if old != id {
+                crate::trace!("\t\tcontent of {:?} has changed", path);
+                self.changes.push(Change { path: path.to_path_buf(), old, new: id });
+            }
+        }
+        Ok(())
+    }
+
+    //provides ids of all files in the tree honoring ignore rules at every level,
+    //unreadable entries are skipped and reported into `errors`,
+    //unreadable files keep their recorded ids
+    pub fn scan(&mut self, root: &Path, ignores: &Ignores, symlinks: SymlinkPolicy, errors: &mut Vec<Error>) {
+        let mut visited = HashSet::new();
+        self.scan_directory(root, ignores, symlinks, &mut vec![], &mut visited, errors);
+    }
+
+    fn scan_directory(&mut self, directory: &Path, ignores: &Ignores, symlinks: SymlinkPolicy,
+                      ancestors: &mut Vec<(u64, u64)>, visited: &mut HashSet<(u64, u64)>,
+                      errors: &mut Vec<Error>) {
+        let unlisted = |source| Error::Unlisted { path: directory.to_path_buf(), source };
+
+        let entries = fs::read_dir(directory)
+            .and_then(|entries| Ok((entries, fs::metadata(directory)?)))
+            .map_err(unlisted);
+
+        let (entries, key) = match entries {
+            Ok((entries, meta)) => {
+                let key = (meta.dev(), meta.ino());
+                //a link to an ancestor would be followed forever
+                if ancestors.contains(&key) {
+                    errors.push(Error::Loop { path: directory.to_path_buf() });
+                    return;
+                }
+                //a directory reached through another link has been scanned already
+                if !visited.insert(key) {
+                    return;
+                }
+                (entries, key)
+            },
+            Err(error) => {
+                errors.push(error);
+                return;
+            }
+        };
+
+        ancestors.push(key);
+        for entry in entries {
+            let path = match entry.map_err(unlisted) {
+                Ok(entry) => entry.path(),
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            let meta = fs::symlink_metadata(&path)
+                .and_then(|meta| {
+                    if meta.file_type().is_symlink() && symlinks == SymlinkPolicy::Follow {
+                        //dangling links are kept as links
+                        fs::metadata(&path).or_else(|error| match error.kind() {
+                            io::ErrorKind::NotFound => Ok(meta),
+                            _ => Err(error)
+                        })
+                    } else {
+                        Ok(meta)
+                    }
+                })
+                .with_path(&path);
+
+            let meta = match meta {
+                Ok(meta) => meta,
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            };
+
+            let kind = if meta.file_type().is_symlink() {
+                match symlinks {
+                    SymlinkPolicy::Skip => continue,
+                    _ => FileKind::Link
+                }
+            } else if meta.is_file() {
+                FileKind::Regular
+            } else {
+                FileKind::Special
+            };
+
+            if ignores.ignored(&path, meta.is_dir()) {
+                continue;
+            }
+
+            if meta.is_dir() {
+                let ignores = ignores.descend(&path, errors);
+                self.scan_directory(&path, &ignores, symlinks, ancestors, visited, errors);
+            } else if let Err(error) = self.provide(&path, kind) {
+                self.keep(&path);
+                errors.push(error);
+            }
+        }
+        ancestors.pop();
+    }
+
+    /// Known paths with changed content since the previous call.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        std::mem::replace(&mut self.changes, vec![])
+    }
+
+    /// Files which ids have changed since the previous call
+    /// only because ids are computed differently now.
+    pub fn take_rehashed(&mut self) -> Vec<Change> {
+        std::mem::replace(&mut self.rehashed, vec![])
+    }
+
+    /// Ids of paths provided since loading.
+    pub fn ids(&self) -> impl Iterator<Item = &Id> {
+        self.id_by_path.values()
+    }
+
+    /// Paths provided since loading with their ids.
+    pub fn entries(&self) -> impl Iterator<Item = (&PathBuf, &Id)> {
+        self.id_by_path.iter()
+    }
+
+    pub fn contains(&self, id: Id) -> bool {
+        self.paths_by_id.contains_key(&id)
+    }
+
+    //records of files which haven't been provided since loading are removed,
+    //so it must be called only after the whole tree has been scanned
+    pub fn prune(&mut self) {
+        let id_by_path = &self.id_by_path;
+        self.records.retain(|path, _| id_by_path.contains_key(path));
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = (&PathBuf, &Record)> {
+        self.records.iter()
+    }
+
+    pub fn forget(&mut self, path: &Path) {
+        self.records.remove(path);
+        if let Some(id) = self.id_by_path.remove(path) {
+            self.unlink(id, path);
+        }
+    }
+
+    //renaming doesn't change content so the files are not re-hashed,
+    //renaming a directory moves everything recorded under it
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        //renaming onto an existing name replaces it
+        for path in self.under(to) {
+            self.forget(&path);
+        }
+
+        for path in self.under(from) {
+            let target = to.join(path.strip_prefix(from).unwrap());
+            if let Some(record) = self.records.remove(&path) {
+                self.records.insert(target.clone(), record);
+            }
+            if let Some(id) = self.id_by_path.remove(&path) {
+                self.unlink(id, &path);
+                self.paths_by_id.entry(id).or_default().insert(target.clone());
+                self.id_by_path.insert(target, id);
+            }
+        }
+    }
+
+    /// MIME type of a provided file, special files have none.
+    pub fn mime(&self, path: &Path) -> Option<&str> {
+        self.records.get(path)
+            .map(|record| record.mime.as_str())
+            .filter(|mime| !mime.is_empty())
+    }
+
+    //special files have no id
+    pub fn id(&mut self, path: &Path) -> Option<Id> {
+        self.id_by_path.get(path).copied()
+    }
+
+    /// Some path provided with `id`; only the current location
+    /// is provided unless the whole tree has been scanned.
+    pub fn path(&self, id: Id) -> Option<&PathBuf> {
+        self.paths(id).next()
+    }
+
+    /// All paths provided with `id`, sorted.
+    pub fn paths(&self, id: Id) -> impl Iterator<Item = &PathBuf> {
+        self.paths_by_id.get(&id)
+            .into_iter()
+            .flat_map(|paths| paths.iter())
+    }
+
+    //an unreadable file is still present, so it keeps the recorded id
+    fn keep(&mut self, path: &Path) {
+        if let Some(id) = self.records.get(path).map(|record| record.id) {
+            self.id_by_path.insert(path.to_path_buf(), id);
+            self.paths_by_id.entry(id).or_default().insert(path.to_path_buf());
+        }
+    }
+
+    //the path itself and everything below it
+    fn under(&self, path: &Path) -> BTreeSet<PathBuf> {
+        self.records.keys()
+            .chain(self.id_by_path.keys())
+            .filter(|known| known.starts_with(path))
+            .cloned()
+            .collect()
+    }
+
+    fn unlink(&mut self, id: Id, path: &Path) {
+        if let Some(paths) = self.paths_by_id.get_mut(&id) {
+            paths.remove(path);
+            if paths.is_empty() {
+                self.paths_by_id.remove(&id);
+            }
+        }
+    }
+}
+
+fn identify(path: &Path, kind: FileKind) -> Result<Id> {
+    match kind {
+        FileKind::Regular => measure("id", || file::id(path)),
+        FileKind::Link => file::link_id(path),
+        FileKind::Special => unreachable!()
+    }
+}
+
+fn sniff(path: &Path, kind: FileKind) -> Result<String> {
+    Ok(mime::sniff(path, kind)?.unwrap_or_default())
+}
+
+//size and modification time, special files have none
+fn stamp(path: &Path, kind: FileKind) -> Result<Option<(u64, u64)>> {
+    let meta = match kind {
+        FileKind::Regular => fs::metadata(path),
+        FileKind::Link => fs::symlink_metadata(path),
+        FileKind::Special => return Ok(None)
+    };
+    let meta = meta.with_path(path)?;
+
+    let modified = meta.modified().with_path(path)?
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0);
+
+    Ok(Some((meta.len(), modified)))
+}
@@ -0,0 +1,276 @@
+use super::Record;
+use crate::model::id::{self, Id};
+use crate::model::entry::FileKind;
+use crate::error::{Error, Result, WithPath};
+
+use std::fs;
+use std::io::ErrorKind;
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+//the header is followed by records of the following layout:
+//id, kind, whether the id is outdated, size, modification time,
+//length of the MIME type and the type, length of the path and the path itself;
+//numbers are big-endian, paths are stored as raw bytes relative to the directory
+//containing the index, so the index stays valid whatever the mount point is
+const MAGIC: &[u8] = b"ARKI";
+const VERSION: u8 = 3;
+//records of the first version have no MIME types, they are sniffed again
+const UNTYPED: u8 = 1;
+//ids of the first two versions hashed stale bytes after the end of files
+const UNFLAGGED: u8 = 2;
+
+pub fn load(path: &Path) -> Result<HashMap<PathBuf, Record>> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            crate::trace!("There is no index yet");
+            return Ok(HashMap::new());
+        },
+        Err(error) => return Err(error).with_path(path)
+    };
+
+    let malformed = || Error::UnexpectedEntry { path: path.to_path_buf() };
+    if !content.starts_with(MAGIC) {
+        return load_text(path, &content);
+    }
+    let version = match content.get(MAGIC.len()) {
+        Some(version) if (UNTYPED..=VERSION).contains(version) => *version,
+        _ => return Err(malformed())
+    };
+
+    let root = path.parent().unwrap();
+    let mut records = HashMap::new();
+    let mut reader = Reader { bytes: &content[MAGIC.len() + 1..] };
+
+    while !reader.bytes.is_empty() {
+        let id = reader.take(id::BINARY_LENGTH).ok_or_else(malformed)?;
+        let id = Id::from_bytes(id)
+            .map_err(|source| Error::MalformedId { path: path.to_path_buf(), source })?;
+
+        let kind = match reader.take(1).ok_or_else(malformed)?[0] {
+            b'r' => FileKind::Regular,
+            b'l' => FileKind::Link,
+            _ => return Err(malformed())
+        };
+
+        let outdated = if version > UNFLAGGED {
+            reader.take(1).ok_or_else(malformed)?[0] != 0
+        } else {
+            true
+        };
+
+        let size = reader.u64().ok_or_else(malformed)?;
+        let modified = reader.u64().ok_or_else(malformed)?;
+
+        let mime = if version > UNTYPED {
+            let length = reader.take(1).ok_or_else(malformed)?[0];
+            let mime = reader.take(length as usize).ok_or_else(malformed)?;
+            String::from_utf8(mime.to_vec()).map_err(|_| malformed())?
+        } else {
+            String::new()
+        };
+
+        let length = reader.u32().ok_or_else(malformed)?;
+        let file = reader.take(length as usize).ok_or_else(malformed)?;
+        let file = root.join(OsStr::from_bytes(file));
+
+        records.insert(file, Record { id, kind, size, modified, mime, outdated });
+    }
+
+    Ok(records)
+}
+
+//the first version was text, one record per line: id, kind, size,
+//modification time and absolute path; its ids are outdated
+fn load_text(path: &Path, content: &[u8]) -> Result<HashMap<PathBuf, Record>> {
+    let malformed = || Error::UnexpectedEntry { path: path.to_path_buf() };
+    let content = std::str::from_utf8(content).map_err(|_| malformed())?;
+
+    let mut records = HashMap::new();
+    for line in content.lines().filter(|line| !line.is_empty()) {
+        let mut parts = line.splitn(5, ' ');
+
+        let id = parts.next().ok_or_else(malformed)?;
+        let id = id.parse::<Id>()
+            .map_err(|source| Error::MalformedId { path: path.to_path_buf(), source })?;
+
+        let kind = match parts.next() {
+            Some("r") => FileKind::Regular,
+            Some("l") => FileKind::Link,
+            _ => return Err(malformed())
+        };
+
+        let size = parts.next()
+            .and_then(|size| size.parse::<u64>().ok())
+            .ok_or_else(malformed)?;
+        let modified = parts.next()
+            .and_then(|modified| modified.parse::<u64>().ok())
+            .ok_or_else(malformed)?;
+
+        let file = parts.next().ok_or_else(malformed)?;
+        records.insert(PathBuf::from(file),
+            Record { id, kind, size, modified, mime: String::new(), outdated: true });
+    }
+
+    Ok(records)
+}
+
+pub fn save(path: &Path, records: &HashMap<PathBuf, Record>) -> Result<()> {
+    let root = path.parent().unwrap();
+    let mut content = MAGIC.to_vec();
+    content.push(VERSION);
+
+    for (file, record) in records.iter() {
+        //files outside of the root can be reached only by following symlinks
+        let file = match file.strip_prefix(root) {
+            Ok(file) => file,
+            Err(_) => continue
+        };
+
+        let kind = match record.kind {
+            FileKind::Regular => b'r',
+            FileKind::Link => b'l',
+            FileKind::Special => continue
+        };
+        let file = file.as_os_str().as_bytes();
+        //too long to be a real type, it is sniffed again
+        let mime = Some(record.mime.as_bytes())
+            .filter(|mime| mime.len() <= u8::MAX as usize)
+            .unwrap_or_default();
+
+        content.extend_from_slice(&record.id.to_bytes());
+        content.push(kind);
+        content.push(record.outdated as u8);
+        content.extend_from_slice(&record.size.to_be_bytes());
+        content.extend_from_slice(&record.modified.to_be_bytes());
+        content.push(mime.len() as u8);
+        content.extend_from_slice(mime);
+        content.extend_from_slice(&(file.len() as u32).to_be_bytes());
+        content.extend_from_slice(file);
+    }
+
+    //a crash while writing must not leave a truncated index
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+
+    fs::write(&temporary, content).with_path(&temporary)?;
+    fs::rename(&temporary, path).with_path(path)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8]
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.bytes.len() < n {
+            return None;
+        }
+
+        let (result, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(result)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.take(8).map(|bytes| u64::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|bytes| u32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir()
+            .join(format!("ark-tagger-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn record(size: u64, kind: FileKind, mime: &str, outdated: bool) -> Record {
+        let id = Id { size, hash: (size as u32).wrapping_mul(7) };
+        Record { id, kind, size, modified: size.wrapping_mul(1_000_000_007), mime: mime.to_owned(), outdated }
+    }
+
+    #[test]
+    fn round_trip() {
+        let directory = directory("round-trip");
+        let index = directory.join("index");
+
+        let mut records = HashMap::new();
+        records.insert(directory.join("plain.jpg"), record(1, FileKind::Regular, "image/jpeg", false));
+        records.insert(directory.join("new\nline"), record(2, FileKind::Regular, "text/plain", true));
+        records.insert(directory.join(OsStr::from_bytes(b"not \xFF utf-8")), record(3, FileKind::Regular, "", false));
+        records.insert(directory.join("nested/dir/link"), record(u64::MAX, FileKind::Link, "inode/symlink", false));
+
+        save(&index, &records).unwrap();
+        assert_eq!(load(&index).unwrap(), records);
+        assert!(!directory.join("index.tmp").exists());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn special_files_and_outside_paths_are_skipped() {
+        let directory = directory("skipped");
+        let index = directory.join("index");
+
+        let mut records = HashMap::new();
+        records.insert(directory.join("fifo"), record(1, FileKind::Special, "", false));
+        records.insert(PathBuf::from("/elsewhere/file"), record(2, FileKind::Regular, "", false));
+
+        save(&index, &records).unwrap();
+        assert!(load(&index).unwrap().is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn text_index_is_read_as_outdated() {
+        let directory = directory("text");
+        let index = directory.join("index");
+        fs::write(&index, "s5_h907060870 r 5 1600000000000000000 /photos/a b.jpg\n\
+                           s3_h1 l 3 1600000000000000001 /photos/link\n").unwrap();
+
+        let records = load(&index).unwrap();
+        assert_eq!(records.len(), 2);
+
+        let record = &records[Path::new("/photos/a b.jpg")];
+        assert_eq!(record.id, Id { size: 5, hash: 907060870 });
+        assert_eq!(record.kind, FileKind::Regular);
+        assert_eq!(record.modified, 1600000000000000000);
+        assert!(record.outdated && record.mime.is_empty());
+        assert_eq!(records[Path::new("/photos/link")].kind, FileKind::Link);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn truncated_index_is_malformed() {
+        let directory = directory("truncated");
+        let index = directory.join("index");
+
+        let mut records = HashMap::new();
+        records.insert(directory.join("file"), record(1, FileKind::Regular, "text/plain", false));
+        save(&index, &records).unwrap();
+
+        let content = fs::read(&index).unwrap();
+        for length in MAGIC.len() + 2..content.len() {
+            fs::write(&index, &content[..length]).unwrap();
+            assert!(load(&index).is_err(), "{} bytes", length);
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
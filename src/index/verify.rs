@@ -0,0 +1,63 @@
+use super::{Index, identify, stamp};
+use crate::model::id::Id;
+use crate::error::Error;
+
+use std::path::PathBuf;
+
+#[derive(Debug, Default)]
+pub struct Verification {
+    pub verified: usize,
+    //size or modification time differ from the recorded ones,
+    //so the change is considered intended
+    pub modified: Vec<PathBuf>,
+    pub missing: Vec<PathBuf>,
+    //content has been changed without a change of modification time
+    pub corrupted: Vec<(PathBuf, Id, Id)>,
+    pub errors: Vec<Error>
+}
+
+impl Verification {
+    pub fn is_clean(&self) -> bool {
+        self.corrupted.is_empty() && self.errors.is_empty()
+    }
+}
+
+impl Index {
+    //re-hashes every recorded file which seems unchanged
+    pub fn verify(&self) -> Verification {
+        let mut result = Verification::default();
+
+        for (path, record) in self.records.iter() {
+            //computed differently, there is nothing to compare with
+            if record.outdated {
+                continue;
+            }
+
+            let current = match stamp(path, record.kind) {
+                Ok(Some(stamp)) => stamp,
+                Ok(None) => continue,
+                Err(Error::Io { source, .. }) if source.kind() == std::io::ErrorKind::NotFound => {
+                    result.missing.push(path.clone());
+                    continue;
+                },
+                Err(error) => {
+                    result.errors.push(error);
+                    continue;
+                }
+            };
+
+            if current != (record.size, record.modified) {
+                result.modified.push(path.clone());
+                continue;
+            }
+
+            match identify(path, record.kind) {
+                Ok(id) if id == record.id => result.verified += 1,
+                Ok(id) => result.corrupted.push((path.clone(), record.id, id)),
+                Err(error) => result.errors.push(error)
+            }
+        }
+
+        result
+    }
+}
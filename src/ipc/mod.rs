@@ -0,0 +1,171 @@
+//! Request/response protocol of the daemon owning a `Model`,
+//! served over a Unix domain socket.
+//!
+//! Every message is one line of JSON. A client sends a `Request`
+//! and receives a `Response`; after `Request::Subscribe` the connection
+//! carries only `Event`s, one per change made by any client.
+
+mod server;
+
+pub use server::serve;
+
+use crate::model::tag::Tag;
+use crate::error::{Error, Result, WithPath};
+
+use serde::{Serialize, Deserialize};
+
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+//relative paths are resolved against the root of the daemon
+//todo: paths which are not valid UTF-8 can't be transferred
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "request", rename_all = "kebab-case")]
+pub enum Request {
+    Tag { paths: Vec<PathBuf>, tag: Tag },
+    Untag { paths: Vec<PathBuf>, tag: Tag },
+    Query { tags: Vec<Tag> },
+    TagsOf { path: PathBuf },
+    Subscribe,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "response", rename_all = "kebab-case")]
+pub enum Response {
+    Done,
+    Paths { paths: Vec<PathBuf> },
+    Tags { tags: Vec<Tag> },
+    Subscribed,
+    Failed { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    Tagged { paths: Vec<PathBuf>, tag: Tag },
+    Untagged { paths: Vec<PathBuf>, tag: Tag },
+}
+
+/// Default socket of the daemon serving `root`.
+pub fn socket_path(root: &Path) -> PathBuf {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(root.as_os_str().as_bytes());
+
+    dirs::runtime_dir()
+        .unwrap_or_else(private_dir)
+        .join(format!("ark-tagger-{:08x}.sock", hasher.finalize()))
+}
+
+//without a runtime directory sockets are kept in a directory of the user
+//inside of the shared temporary one, it is created by the daemon
+fn private_dir() -> PathBuf {
+    env::temp_dir().join(format!("ark-tagger-{}", unsafe { libc::getuid() }))
+}
+
+/// Connection to a running daemon.
+pub struct Client {
+    path: PathBuf,
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    pub fn connect(path: &Path) -> Result<Self> {
+        let writer = UnixStream::connect(path).with_path(path)?;
+        let reader = BufReader::new(writer.try_clone().with_path(path)?);
+        Ok(Client { path: path.to_path_buf(), reader, writer })
+    }
+
+    pub fn request(&mut self, request: &Request) -> Result<Response> {
+        send(&mut self.writer, request).with_path(&self.path)?;
+
+        match receive(&mut self.reader).with_path(&self.path)? {
+            Some(Response::Failed { message }) => Err(Error::Daemon { message }),
+            Some(response) => Ok(response),
+            None => Err(Error::Daemon { message: "connection closed".to_owned() })
+        }
+    }
+
+    pub fn tag(&mut self, paths: Vec<PathBuf>, tag: &Tag) -> Result<()> {
+        let response = self.request(&Request::Tag { paths, tag: tag.clone() })?;
+        expect(response, |response| match response {
+            Response::Done => Some(()),
+            _ => None
+        })
+    }
+
+    pub fn untag(&mut self, paths: Vec<PathBuf>, tag: &Tag) -> Result<()> {
+        let response = self.request(&Request::Untag { paths, tag: tag.clone() })?;
+        expect(response, |response| match response {
+            Response::Done => Some(()),
+            _ => None
+        })
+    }
+
+    pub fn query(&mut self, tags: Vec<Tag>) -> Result<Vec<PathBuf>> {
+        let response = self.request(&Request::Query { tags })?;
+        expect(response, |response| match response {
+            Response::Paths { paths } => Some(paths),
+            _ => None
+        })
+    }
+
+    pub fn tags_of(&mut self, path: PathBuf) -> Result<Vec<Tag>> {
+        let response = self.request(&Request::TagsOf { path })?;
+        expect(response, |response| match response {
+            Response::Tags { tags } => Some(tags),
+            _ => None
+        })
+    }
+
+    /// Turns the connection into a blocking stream of events.
+    pub fn subscribe(mut self) -> Result<Events> {
+        let response = self.request(&Request::Subscribe)?;
+        expect(response, |response| match response {
+            Response::Subscribed => Some(()),
+            _ => None
+        })?;
+
+        Ok(Events { path: self.path, reader: self.reader })
+    }
+}
+
+pub struct Events {
+    path: PathBuf,
+    reader: BufReader<UnixStream>,
+}
+
+impl Iterator for Events {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        receive(&mut self.reader)
+            .with_path(&self.path)
+            .transpose()
+    }
+}
+
+fn expect<T, F>(response: Response, f: F) -> Result<T>
+where F: FnOnce(Response) -> Option<T> {
+    let message = format!("unexpected response: {:?}", response);
+    f(response).ok_or(Error::Daemon { message })
+}
+
+//malformed messages are reported as `InvalidData`
+fn send<W: Write, T: Serialize>(writer: &mut W, message: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes())?;
+    writer.flush()
+}
+
+fn receive<R: BufRead, T: for<'de> Deserialize<'de>>(reader: &mut R) -> std::io::Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
@@ -0,0 +1,198 @@
+use super::{Request, Response, Event, send};
+use crate::model::{Model, id::Id};
+use crate::xattrs;
+use crate::error::{Error, Result, WithPath};
+
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc::{self, Sender}};
+use std::thread;
+
+struct State {
+    model: Model,
+    subscribers: Vec<Sender<Event>>,
+}
+
+/// Serves requests on `socket` until the process is killed,
+/// every connection is handled in its own thread.
+pub fn serve(model: Model, socket: &Path) -> Result<()> {
+    if let Some(directory) = socket.parent() {
+        if directory == super::private_dir() {
+            prepare(directory)?;
+        }
+    }
+
+    //a socket left by a killed daemon is removed
+    if socket.exists() {
+        if UnixStream::connect(socket).is_ok() {
+            let message = format!("another daemon is listening on {:?}", socket);
+            return Err(Error::Daemon { message });
+        }
+        fs::remove_file(socket).with_path(socket)?;
+    }
+
+    let listener = UnixListener::bind(socket).with_path(socket)?;
+    //only the user can connect whatever the umask is
+    fs::set_permissions(socket, fs::Permissions::from_mode(0o600)).with_path(socket)?;
+    let state = Arc::new(Mutex::new(State { model, subscribers: vec![] }));
+
+    for stream in listener.incoming() {
+        let stream = match stream.with_path(socket) {
+            Ok(stream) => stream,
+            Err(error) => {
+                println!("WARNING: {}", error);
+                continue;
+            }
+        };
+
+        let state = state.clone();
+        let socket = socket.to_path_buf();
+        thread::spawn(move || {
+            if let Err(error) = handle(stream, state).with_path(&socket) {
+                println!("WARNING: {}", error);
+            }
+        });
+    }
+    Ok(())
+}
+
+//other users could replace the socket in a directory they have created
+fn prepare(directory: &Path) -> Result<()> {
+    fs::DirBuilder::new()
+        .mode(0o700)
+        .create(directory)
+        .or_else(|error| match error.kind() {
+            ErrorKind::AlreadyExists => Ok(()),
+            _ => Err(error)
+        })
+        .with_path(directory)?;
+
+    let meta = fs::symlink_metadata(directory).with_path(directory)?;
+    if !meta.is_dir() || meta.uid() != unsafe { libc::getuid() } || meta.mode() & 0o077 != 0 {
+        let message = format!("{:?} must be a directory accessible only by the user", directory);
+        return Err(Error::Daemon { message });
+    }
+    Ok(())
+}
+
+fn handle(stream: UnixStream, state: Arc<Mutex<State>>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        crate::trace!("\tRequest: {}", line.trim_end());
+        let request = serde_json::from_str::<Request>(&line);
+        line.clear();
+
+        let response = match request {
+            Ok(Request::Subscribe) => {
+                let (sender, receiver) = mpsc::channel();
+                state.lock().unwrap().subscribers.push(sender);
+                send(&mut writer, &Response::Subscribed)?;
+
+                //the subscriber is dropped on the next event after disconnection
+                for event in receiver {
+                    send(&mut writer, &event)?;
+                }
+                return Ok(());
+            },
+            Ok(request) => state.lock().unwrap().process(request),
+            Err(error) => Response::Failed { message: error.to_string() }
+        };
+
+        send(&mut writer, &response)?;
+    }
+    Ok(())
+}
+
+impl State {
+    fn process(&mut self, request: Request) -> Response {
+        self.execute(request)
+            .unwrap_or_else(|error| Response::Failed { message: error.to_string() })
+    }
+
+    fn execute(&mut self, request: Request) -> Result<Response> {
+        match request {
+            Request::Tag { paths, tag } => {
+                if tag.is_empty() {
+                    return Err(Error::EmptyTag);
+                }
+                let paths = self.resolve(paths)?;
+                let (paths, ids) = self.ids(paths)?;
+                if ids.is_empty() {
+                    return Ok(Response::Done);
+                }
+                self.model.database.insert(ids.into_iter(), &tag)?;
+                self.save();
+
+                self.notify(Event::Tagged { paths, tag });
+                Ok(Response::Done)
+            },
+            Request::Untag { paths, tag } => {
+                let paths = self.resolve(paths)?;
+                let (paths, ids) = self.ids(paths)?;
+                if ids.is_empty() {
+                    return Ok(Response::Done);
+                }
+                self.model.database.remove(ids.into_iter(), &tag)?;
+                self.save();
+
+                self.notify(Event::Untagged { paths, tag });
+                Ok(Response::Done)
+            },
+            Request::Query { tags } => {
+                Ok(Response::Paths { paths: self.model.query(&tags) })
+            },
+            Request::TagsOf { path } => {
+                let path = self.resolve(vec![path])?.remove(0);
+                let tags = match self.model.id_of(&path)? {
+                    Some(id) => self.model.database.tags_of(id).cloned().collect(),
+                    None => vec![]
+                };
+                Ok(Response::Tags { tags })
+            },
+            //handled by the connection itself
+            Request::Subscribe => unreachable!()
+        }
+    }
+
+    fn resolve(&self, paths: Vec<PathBuf>) -> Result<Vec<PathBuf>> {
+        paths.iter()
+            .map(|path| self.model.resolve(path))
+            .collect()
+    }
+
+    //paths without ids, e.g. directories, are left out
+    fn ids(&mut self, paths: Vec<PathBuf>) -> Result<(Vec<PathBuf>, Vec<Id>)> {
+        let mut identified = (vec![], vec![]);
+        for path in paths {
+            if let Some(id) = self.model.id_of(&path)? {
+                identified.0.push(path);
+                identified.1.push(id);
+            }
+        }
+        Ok(identified)
+    }
+
+    //ids of newly tagged files must survive a restart
+    fn save(&mut self) {
+        if let Err(error) = self.model.index.save() {
+            println!("WARNING: {}", error);
+        }
+        for error in self.model.database.take_errors() {
+            println!("WARNING: {}", error);
+        }
+        for error in xattrs::write_through(&mut self.model) {
+            println!("WARNING: {}", error);
+        }
+    }
+
+    fn notify(&mut self, event: Event) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
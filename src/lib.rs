@@ -0,0 +1,51 @@
+//! Tag store of ark-tagger, usable without the graphical interface.
+//!
+//! Files are identified by their content (`Id`), so tags survive renaming
+//! and moving. Every root has its own `Index` of paths and ids,
+//! while the `Database` of tags can be shared by several roots.
+//!
+//! ```no_run
+//! use ark_tagger::{Config, Model, MigrationPolicy};
+//!
+//! let config = Config::load()?;
+//! let mut model = Model::new("/home/me/photos".into(), MigrationPolicy::Ignore, &config)?;
+//! model.scan();
+//!
+//! let ids: Vec<_> = model.index.ids().cloned().map(Some).collect();
+//! let cats = vec!["cats".to_owned()];
+//! let filter = model.database.filter(ids.iter().copied(), cats.iter());
+//! # Ok::<(), ark_tagger::Error>(())
+//! ```
+//!
+//! Items re-exported at the crate root, their methods and public fields
+//! form the stable API and follow semantic versioning: while the version
+//! is `0.x`, breaking changes bump the minor version. Modules are public
+//! for tools which need more control, their contents may change in any release.
+//!
+//! The library reads no configuration by itself, `Config` is passed
+//! explicitly. Debugging output is printed only with the `trace` feature.
+
+mod file;
+pub mod model;
+pub mod database;
+pub mod index;
+pub mod utils;
+pub mod error;
+pub mod volume;
+pub mod config;
+pub mod ipc;
+pub mod http;
+pub mod farm;
+pub mod xattrs;
+pub mod xmp;
+pub mod sidecar;
+pub mod import;
+pub mod mime;
+#[cfg(feature = "filesystem")]
+pub mod tagfs;
+
+pub use model::{Model, id::Id, tag::Tag, location::Location};
+pub use database::Database;
+pub use index::{Index, Change, MigrationPolicy, Hashing};
+pub use error::{Error, Result};
+pub use config::Config;
@@ -1,42 +1,28 @@
-#![feature(try_trait)]
-
 mod app;
-mod file;
-mod model;
-mod database;
-mod index;
-mod utils;
+mod cli;
+mod commands;
 
-#[macro_use]
-extern crate lazy_static;
+use ark_tagger::Config;
 
 use iced::{Settings, Application};
 use iced::window;
+use structopt::StructOpt;
 
-use std::path::PathBuf;
-use std::str::FromStr;
 use std::env;
-
-lazy_static! {
-    static ref DATA_NAME: &'static str = ".ark-tags.data";
-    static ref INDEX_NAME: &'static str = ".ark-tags.index";
-    //todo: it is assumed that the Database can be persisted
-    // separately from the Index; this way it is possible
-    // to have an Index for every root and
-    // one Database across all roots
-}
+use std::process;
 
 fn main() {
-    let mut args = env::args();
-    args.next();
-
-    let root = args.next();
-    let root = root
-        .map(|path|
-            PathBuf::from_str(&path)
-                .map_err(|err| println!("WARNING: {}", err))
-                .ok())
-        .unwrap_or_else(||
+    let mut options = cli::Options::from_args();
+    let mut config = Config::load()
+        .unwrap_or_else(|error| {
+            println!("WARNING: {}", error);
+            Config::default()
+        });
+    options.apply(&mut config);
+
+    let root = options.root.take()
+        .or_else(|| config.roots.first().cloned())
+        .or_else(||
             env::current_dir()
                 .map_err(|err| println!("WARNING: {}", err))
                 .ok());
@@ -45,14 +31,20 @@ fn main() {
         .canonicalize().unwrap();
 
     println!("Root: {:?}", root);
+    options.root = Some(root);
+
+    if let Some(command) = options.command.take() {
+        process::exit(commands::run(command, &options, &config));
+    }
 
+    let size = (config.window.width, config.window.height);
     app::RootWidget::run(Settings {
         window: window::Settings {
-            size: (480,480),
+            size,
             resizable: true,
             decorations: true
         },
-        flags: root,
+        flags: (options, config),
 
         ..Default::default()
     })
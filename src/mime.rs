@@ -0,0 +1,57 @@
+//! MIME types of files, sniffed from magic bytes at the start of content
+//! and guessed from the extension when the content is not recognized.
+
+use crate::model::entry::FileKind;
+use crate::error::{Result, WithPath};
+
+use infer::Infer;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+pub const UNKNOWN: &str = "application/octet-stream";
+pub const TEXT: &str = "text/plain";
+//links are indexed themselves, not their targets
+pub const SYMLINK: &str = "inode/symlink";
+
+//enough for all known signatures
+const SNIFFED: u64 = 8192;
+
+/// MIME type of a file, special files have none.
+pub fn sniff(path: &Path, kind: FileKind) -> Result<Option<String>> {
+    match kind {
+        FileKind::Regular => {},
+        FileKind::Link => return Ok(Some(SYMLINK.to_owned())),
+        FileKind::Special => return Ok(None)
+    }
+
+    let mut bytes = vec![];
+    File::open(path)
+        .and_then(|file| file.take(SNIFFED).read_to_end(&mut bytes))
+        .with_path(path)?;
+
+    if let Some(sniffed) = Infer::new().get(&bytes) {
+        return Ok(Some(sniffed.mime));
+    }
+    if let Some(guessed) = mime_guess::from_path(path).first_raw() {
+        return Ok(Some(guessed.to_owned()));
+    }
+    Ok(Some(if is_text(&bytes) { TEXT } else { UNKNOWN }.to_owned()))
+}
+
+/// Top-level type, e.g. `image` of `image/jpeg`.
+pub fn top_level(mime: &str) -> &str {
+    mime.split('/').next().unwrap_or(mime)
+}
+
+//the last character can be cut off by the limit
+fn is_text(bytes: &[u8]) -> bool {
+    if bytes.is_empty() || bytes.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(bytes) {
+        Ok(_) => true,
+        Err(error) => error.error_len().is_none()
+    }
+}
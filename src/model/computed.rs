@@ -0,0 +1,86 @@
+//! Pseudo-tags computed from properties of files: `ext:jpg`, `modified:2019`
+//! and `modified:2019-05` of the local modification time, `size:<1MB`, and
+//! `mime:image/jpeg` with `mime:image` of the type recorded in the index.
+//!
+//! Computed tags are never stored in the database. A file has a tag
+//! when it is tagged with it or when the tag is computed for it,
+//! so stored tags in the same namespaces still match. Namespaces
+//! of importers, e.g. `year:` of music, are not used.
+
+use super::entry::FileEntry;
+use super::id::Id;
+use super::tag::Tag;
+use crate::database::Database;
+use crate::mime;
+use crate::utils::Filter;
+
+use chrono::{DateTime, Local};
+
+use std::collections::BTreeSet;
+use std::ffi::OsStr;
+use std::path::Path;
+use std::time::SystemTime;
+
+pub const NAMESPACES: &[&str] = &["ext:", "modified:", "size:", "mime:"];
+
+const KB: u64 = 1 << 10;
+const MB: u64 = 1 << 20;
+const GB: u64 = 1 << 30;
+
+//upper bounds, larger files are `size:>1GB`
+const SIZES: &[(u64, &str)] = &[(KB, "<1KB"), (MB, "<1MB"), (100 * MB, "<100MB"), (GB, "<1GB")];
+
+/// Whether `tag` is in a namespace of computed tags.
+pub fn is_computed(tag: &str) -> bool {
+    NAMESPACES.iter().any(|namespace| tag.starts_with(namespace))
+}
+
+/// Computed tags of a file with `name`, `size` bytes long, modified at `modified`,
+/// of type `mime` if it is known.
+pub fn tags(name: &OsStr, size: u64, modified: SystemTime, mime: Option<&str>) -> BTreeSet<Tag> {
+    let mut tags = BTreeSet::new();
+
+    if let Some(extension) = Path::new(name).extension() {
+        tags.insert(format!("ext:{}", extension.to_string_lossy().to_lowercase()));
+    }
+
+    let modified: DateTime<Local> = modified.into();
+    tags.insert(modified.format("modified:%Y").to_string());
+    tags.insert(modified.format("modified:%Y-%m").to_string());
+
+    let size = SIZES.iter()
+        .find(|(bound, _)| size < *bound)
+        .map(|(_, label)| *label)
+        .unwrap_or(">1GB");
+    tags.insert(format!("size:{}", size));
+
+    if let Some(mime) = mime {
+        tags.insert(format!("mime:{}", mime));
+        tags.insert(format!("mime:{}", mime::top_level(mime)));
+    }
+
+    tags
+}
+
+/// Computed tags of `entry`.
+pub fn of(entry: &FileEntry) -> BTreeSet<Tag> {
+    tags(&entry.name, entry.size, entry.modified, entry.mime.as_deref())
+}
+
+/// For every file, whether it has all of `tags`, stored or computed;
+/// `computed` are computed tags of the files, missing ones count as none.
+pub fn filter<'b, T>(database: &Database, ids: &[Option<Id>], computed: &[BTreeSet<Tag>], tags: T) -> Filter
+    where T: Iterator<Item = &'b Tag> {
+    let mut filter = vec![true; ids.len()];
+
+    for tag in tags {
+        let stored = database.filter(ids.iter().copied(), std::iter::once(tag));
+        for (i, (matches, stored)) in filter.iter_mut().zip(stored).enumerate() {
+            let computed = computed.get(i)
+                .map(|computed| computed.contains(tag))
+                .unwrap_or(false);
+            *matches = *matches && (stored || computed);
+        }
+    }
+    filter
+}
@@ -1,21 +1,71 @@
+use serde::Deserialize;
+
+use std::ffi::OsString;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymlinkPolicy {
+    Follow,
+    Skip,
+    //the link is tagged itself, its id is computed from the target path
+    Link
+}
+
+impl Default for SymlinkPolicy {
+    fn default() -> Self {
+        SymlinkPolicy::Follow
+    }
+}
+
+impl FromStr for SymlinkPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "follow" => Ok(SymlinkPolicy::Follow),
+            "skip" => Ok(SymlinkPolicy::Skip),
+            "link" => Ok(SymlinkPolicy::Link),
+            _ => Err(format!("unknown symlink policy: {}", s))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Regular,
+    Link,
+    //sockets, FIFOs and device nodes are listed but never read
+    Special
+}
+
+impl FileKind {
+    pub fn hashable(&self) -> bool {
+        *self != FileKind::Special
+    }
+}
 
 #[derive(Debug)]
 pub struct DirEntry {
-    pub name: String,
+    pub name: OsString,
     pub path: PathBuf,
 }
 
 #[derive(Debug)]
 pub struct FileEntry {
-    pub name: String,
+    pub name: OsString,
     pub path: PathBuf,
+    pub kind: FileKind,
 
     pub size: u64,
     pub created: SystemTime,
     pub modified: SystemTime,
     pub accessed: SystemTime,
+
+    //recorded in the index, special files have none
+    pub mime: Option<String>,
 }
 
 impl FileEntry {
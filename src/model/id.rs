@@ -1,8 +1,8 @@
 use std::str::FromStr;
 use derive_more::From;
 use std::string::ToString;
-use std::option::NoneError;
 use std::num::ParseIntError;
+use std::convert::TryInto;
 
 //todo: remove necessity in Copy
 #[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -19,22 +19,114 @@ impl ToString for Id {
 
 #[derive(Debug, From)]
 pub enum ParseError {
-    NotEnoughTokens(NoneError),
-    UnexpectedToken(ParseIntError)
+    //the expected prefix of the token
+    MissingPrefix(char),
+    MissingSeparator,
+    //leading zeros and signs would break the round-trip
+    NonCanonical(String),
+    UnexpectedToken(ParseIntError),
+    WrongLength(usize)
 }
 
+//the text form is `s<size>_h<hash>`, nothing else is accepted,
+//so that `id.to_string().parse()` and `s.parse()?.to_string()` are identities
 impl FromStr for Id {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split('_');
+        let mut parts = s.splitn(2, '_');
 
-        let size = parts.next()?;
-        let size = size.trim_start_matches('s').parse()?;
+        let size = parts.next().unwrap_or_default();
+        let size = parse_token(size, 's')?;
 
-        let hash = parts.next()?;
-        let hash = hash.trim_start_matches('h').parse()?;
+        let hash = parts.next().ok_or(ParseError::MissingSeparator)?;
+        let hash = parse_token(hash, 'h')?;
 
         Ok(Id { size, hash })
     }
-}
\ No newline at end of file
+}
+
+fn parse_token<T: FromStr<Err = ParseIntError>>(token: &str, prefix: char) -> Result<T, ParseError> {
+    if !token.starts_with(prefix) {
+        return Err(ParseError::MissingPrefix(prefix));
+    }
+
+    let digits = &token[1..];
+    if !digits.bytes().all(|c| c.is_ascii_digit()) ||
+        (digits.len() > 1 && digits.starts_with('0')) {
+        return Err(ParseError::NonCanonical(token.to_owned()));
+    }
+
+    Ok(digits.parse()?)
+}
+
+//the binary form is fixed-width: size and hash in big-endian,
+//so that the bytes are ordered in the same way as ids are
+pub const BINARY_LENGTH: usize = 12;
+
+impl Id {
+    pub fn to_bytes(&self) -> [u8; BINARY_LENGTH] {
+        let mut result = [0; BINARY_LENGTH];
+        result[..8].copy_from_slice(&self.size.to_be_bytes());
+        result[8..].copy_from_slice(&self.hash.to_be_bytes());
+        result
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseError> {
+        if bytes.len() != BINARY_LENGTH {
+            return Err(ParseError::WrongLength(bytes.len()));
+        }
+
+        let size = u64::from_be_bytes(bytes[..8].try_into().unwrap());
+        let hash = u32::from_be_bytes(bytes[8..].try_into().unwrap());
+        Ok(Id { size, hash })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDS: &[Id] = &[
+        Id { size: 0, hash: 0 },
+        Id { size: 1, hash: 10 },
+        Id { size: 1024, hash: 3735928559 },
+        Id { size: u64::MAX, hash: u32::MAX },
+    ];
+
+    #[test]
+    fn text_round_trip() {
+        for id in IDS {
+            assert_eq!(id.to_string().parse::<Id>().unwrap(), *id);
+        }
+        for text in &["s0_h0", "s1024_h3735928559", "s18446744073709551615_h4294967295"] {
+            assert_eq!(text.parse::<Id>().unwrap().to_string(), *text);
+        }
+    }
+
+    #[test]
+    fn text_is_strict() {
+        for text in &["", "s1", "s1_", "1_h1", "s1_1", "s01_h1", "s1_h01", "s+1_h1",
+                      "s1_h-1", "s 1_h1", "s1_h1_", "s1_h1 ", "s1_h4294967296", "S1_H1"] {
+            assert!(text.parse::<Id>().is_err(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        for id in IDS {
+            assert_eq!(Id::from_bytes(&id.to_bytes()).unwrap(), *id);
+        }
+        assert!(Id::from_bytes(&[0; BINARY_LENGTH - 1]).is_err());
+        assert!(Id::from_bytes(&[0; BINARY_LENGTH + 1]).is_err());
+    }
+
+    #[test]
+    fn binary_order_is_id_order() {
+        for a in IDS {
+            for b in IDS {
+                assert_eq!(a.cmp(b), a.to_bytes().cmp(&b.to_bytes()));
+            }
+        }
+    }
+}
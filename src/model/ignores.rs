@@ -0,0 +1,109 @@
+use crate::error::Error;
+use crate::sidecar;
+use crate::config::Names;
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+use std::ffi::OsStr;
+
+//gitignore-style rules from `.arkignore` files,
+//the deeper a file is the higher its priority
+#[derive(Clone)]
+pub struct Ignores {
+    root: PathBuf,
+    builtin: Vec<String>,
+    //name of the ignore files
+    name: String,
+    //patterns of the configuration, lower priority than any `.arkignore`
+    patterns: Gitignore,
+    //from the root down to the current directory
+    matchers: Vec<Gitignore>,
+    show_hidden: bool,
+}
+
+impl Ignores {
+    pub fn root(root: &Path, names: &Names, patterns: &[String], errors: &mut Vec<Error>) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in patterns {
+            if let Err(error) = builder.add_line(None, pattern) {
+                errors.push(Error::Ignore { path: root.to_path_buf(), message: error.to_string() });
+            }
+        }
+        let patterns = builder.build().unwrap_or_else(|error| {
+            errors.push(Error::Ignore { path: root.to_path_buf(), message: error.to_string() });
+            Gitignore::empty()
+        });
+
+        let ignores = Ignores {
+            root: root.to_path_buf(),
+            builtin: names.builtin(),
+            name: names.ignore.clone(),
+            patterns,
+            matchers: vec![],
+            show_hidden: false,
+        };
+
+        ignores.descend(root, errors)
+    }
+
+    pub fn descend(&self, directory: &Path, errors: &mut Vec<Error>) -> Self {
+        let path = directory.join(&self.name);
+        let (matcher, error) = Gitignore::new(&path);
+
+        if let Some(error) = error {
+            errors.push(Error::Ignore { path, message: error.to_string() });
+        }
+
+        let mut result = self.clone();
+        result.matchers.push(matcher);
+        result
+    }
+
+    pub fn ascend(&self) -> Self {
+        let mut result = self.clone();
+        result.matchers.pop();
+        result
+    }
+
+    pub fn show_hidden(&self) -> bool {
+        self.show_hidden
+    }
+
+    pub fn with_hidden(&self, show_hidden: bool) -> Self {
+        Ignores { show_hidden, ..self.clone() }
+    }
+
+    pub fn ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let name = path.file_name().unwrap_or_default();
+
+        if path.parent() == Some(self.root.as_path()) &&
+            self.builtin.iter().any(|builtin| OsStr::new(builtin) == name) {
+            return true;
+        }
+
+        //sidecars belong to their files
+        if !is_dir && sidecar::is_sidecar(path) {
+            return true;
+        }
+
+        for matcher in self.matchers.iter().rev() {
+            let matched = matcher.matched(path, is_dir);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+
+        let matched = self.patterns.matched(path, is_dir);
+        if matched.is_ignore() {
+            return true;
+        }
+        if matched.is_whitelist() {
+            return false;
+        }
+
+        !self.show_hidden && name.to_string_lossy().starts_with('.')
+    }
+}
@@ -1,126 +1,273 @@
-use super::entry::{DirEntry, FileEntry};
+use super::entry::{DirEntry, FileEntry, FileKind, SymlinkPolicy};
+use super::ignores::Ignores;
 use crate::index::Index;
+use crate::config::Names;
+use crate::error::{Error, Result, WithPath};
 
 use std::path::{Path, PathBuf};
+use std::ffi::OsString;
 use std::fs;
 
+/// Listing of a directory in a root, honoring ignore rules.
 pub struct Location {
     pub directories: Vec<DirEntry>,
     pub files: Vec<FileEntry>,
     pub depth: usize,
 
-    ignores: Vec<String>,
-    path: PathBuf
+    ignores: Ignores,
+    symlinks: SymlinkPolicy,
+    path: PathBuf,
+
+    //entries which can't be loaded are skipped
+    errors: Vec<Error>
 }
 
 impl Location {
-    pub fn root(path: PathBuf, ignores: Vec<String>, index: &mut Index) -> Self {
-        let path = path.canonicalize().unwrap();
-
-        let (directories, files) = Self::list_entries(&path, Some(&ignores));
-        for file in files.iter() {
-            index.provide(&file.path);
-        }
+    pub fn root(path: PathBuf, names: &Names, patterns: &[String],
+                symlinks: SymlinkPolicy, index: &mut Index) -> Result<Self> {
+        let path = path.canonicalize().with_path(&path)?;
 
-        Location { directories, files, depth: 0, ignores, path }
+        let mut errors = vec![];
+        let ignores = Ignores::root(&path, names, patterns, &mut errors);
+        Self::at(path, 0, ignores, symlinks, errors, index)
     }
 
-    pub fn ascend(&self, index: &mut Index) -> Self {
+    pub fn ascend(&self, index: &mut Index) -> Result<Self> {
         assert!(self.depth > 0);
 
         let parent = self.path.parent().unwrap();
-        println!("\t\tpath: {:?}", parent);
+        crate::trace!("\t\tpath: {:?}", parent);
 
-        let ignores = if self.depth == 1 {
-            Some(&self.ignores)
-        } else {
-            None
+        Self::at(parent.to_path_buf(), self.depth - 1, self.ignores.ascend(), self.symlinks, vec![], index)
+    }
+
+    pub fn descend(&self, index: &mut Index, i: usize) -> Result<Self> {
+        let target: &DirEntry = &self.directories[i];
+        let target = &target.path;
+        crate::trace!("\t\tpath: {:?}", target);
+
+        let mut errors = vec![];
+        let ignores = self.ignores.descend(target, &mut errors);
+        Self::at(target.clone(), self.depth + 1, ignores, self.symlinks, errors, index)
+    }
+
+    //lists the same directory again, e.g. when its ignore rules have been changed
+    pub fn reload(&self, index: &mut Index, show_hidden: bool) -> Result<Self> {
+        let mut errors = vec![];
+        let ignores = self.ignores.ascend()
+            .descend(&self.path, &mut errors)
+            .with_hidden(show_hidden);
+        Self::at(self.path.clone(), self.depth, ignores, self.symlinks, errors, index)
+    }
+
+    pub fn show_hidden(&self) -> bool {
+        self.ignores.show_hidden()
+    }
+
+    pub fn activate(&self, i: usize) -> Result<()> {
+        let path = &self.files[i].path;
+        crate::trace!("\t\tpath {:?}", path);
+        opener::open(path)
+            .map_err(|error| Error::Open {
+                path: path.clone(),
+                message: error.to_string()
+            })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn take_errors(&mut self) -> Vec<Error> {
+        std::mem::replace(&mut self.errors, vec![])
+    }
+
+    //the following methods apply filesystem changes
+    //and return false if the change is not visible in this location
+
+    pub fn created(&mut self, index: &mut Index, path: &Path) -> bool {
+        if self.contains(path) {
+            return self.modified(index, path);
+        }
+
+        match self.insert(index, path) {
+            Ok(visible) => visible,
+            Err(error) => {
+                self.errors.push(error);
+                false
+            }
+        }
+    }
+
+    pub fn removed(&mut self, index: &mut Index, path: &Path) -> bool {
+        let n = self.directories.len() + self.files.len();
+
+        self.directories.retain(|entry| entry.path != path);
+        self.files.retain(|entry| entry.path != path);
+        index.forget(path);
+
+        n != self.directories.len() + self.files.len()
+    }
+
+    pub fn modified(&mut self, index: &mut Index, path: &Path) -> bool {
+        let position = self.files.iter()
+            .position(|entry| entry.path == path);
+
+        let i = match position {
+            Some(i) => i,
+            None => return false
         };
 
-        let (directories, files) = Self::list_entries(&parent, ignores);
-        for file in files.iter() {
-            index.provide(&file.path);
+        let entry = self.load_entry(path)
+            .and_then(|entry| match entry {
+                Some(Entry::File(mut entry)) => {
+                    index.provide(&entry.path, entry.kind)?;
+                    entry.mime = index.mime(&entry.path).map(String::from);
+                    Ok(Some(entry))
+                },
+                _ => Ok(None)
+            });
+
+        match entry {
+            Ok(Some(entry)) => self.files[i] = entry,
+            Ok(None) => {
+                self.files.remove(i);
+            },
+            Err(error) => {
+                self.errors.push(error);
+                self.files.remove(i);
+                index.forget(path);
+            }
+        }
+        true
+    }
+
+    pub fn moved(&mut self, index: &mut Index, from: &Path, to: &Path) -> bool {
+        if !self.contains(from) {
+            return self.created(index, to);
+        }
+        if to.parent() != Some(self.path.as_path()) {
+            return self.removed(index, from);
         }
 
-        Location {
-            directories,
-            files,
-            depth: self.depth - 1,
+        //renaming onto an existing name replaces it
+        self.directories.retain(|entry| entry.path != to);
+        self.files.retain(|entry| entry.path != to);
+        index.rename(from, to);
 
-            ignores: self.ignores.clone(),
-            path: parent.to_path_buf()
+        let name = Self::name(to);
+
+        if let Some(entry) = self.directories.iter_mut().find(|entry| entry.path == from) {
+            entry.name = name;
+            entry.path = to.to_path_buf();
+        } else if let Some(entry) = self.files.iter_mut().find(|entry| entry.path == from) {
+            entry.name = name;
+            entry.path = to.to_path_buf();
         }
+        true
     }
 
-    pub fn descend(&self, index: &mut Index, i: usize) -> Self {
-        let target: &DirEntry = &self.directories[i];
-        let target = &target.path;
-        println!("\t\tpath: {:?}", target);
+    fn at(path: PathBuf, depth: usize, ignores: Ignores, symlinks: SymlinkPolicy,
+          errors: Vec<Error>, index: &mut Index) -> Result<Self> {
+        let entries = fs::read_dir(&path).with_path(&path)?;
+
+        let mut location = Location {
+            directories: vec![],
+            files: vec![],
+            depth,
 
-        let (directories, files) = Self::list_entries(&target, None);
-        for file in files.iter() {
-            index.provide(&file.path);
+            ignores,
+            symlinks,
+            path,
+
+            errors
+        };
+
+        for entry in entries {
+            let result = entry
+                .with_path(&location.path)
+                .and_then(|entry| location.insert(index, &entry.path()));
+
+            if let Err(error) = result {
+                location.errors.push(error);
+            }
         }
 
-        Location {
-            directories,
-            files,
-            depth: self.depth + 1,
+        Ok(location)
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        self.directories.iter().any(|entry| entry.path == path) ||
+            self.files.iter().any(|entry| entry.path == path)
+    }
 
-            ignores: self.ignores.clone(),
-            path: target.clone()
+    fn insert(&mut self, index: &mut Index, path: &Path) -> Result<bool> {
+        match self.load_entry(path)? {
+            Some(Entry::Dir(entry)) => self.directories.push(entry),
+            Some(Entry::File(mut entry)) => {
+                index.provide(&entry.path, entry.kind)?;
+                entry.mime = index.mime(&entry.path).map(String::from);
+                self.files.push(entry);
+            },
+            None => return Ok(false)
         }
+        Ok(true)
     }
 
-    pub fn activate(&self, i: usize) {
-        let path = &self.files[i].path;
-        println!("\t\tpath {:?}", path);
-        opener::open(path).unwrap();
-    }
-
-    fn list_entries(path: &Path, ignores: Option<&Vec<String>>) -> (Vec<DirEntry>, Vec<FileEntry>) {
-        let entries = fs::read_dir(&path)
-            .unwrap()
-            .map(|entry| entry.unwrap())
-            .filter_map(move |entry| {
-                let name = entry.file_name().into_string().unwrap();
-                if name.starts_with('.') ||
-                    ignores.map(|ignores| ignores.contains(&name))
-                        .unwrap_or(false) {
-                    None
-                } else {
-                    if entry.file_type().unwrap().is_dir() {
-                        Some(Entry::Dir(DirEntry { name, path: entry.path() }))
-                    } else {
-                        let path = entry.path();
-                        let meta = entry.metadata().unwrap();
-
-                        Some(Entry::File(FileEntry {
-                            name,
-                            path,
-
-                            size: meta.len(),
-                            created: meta.created().unwrap(),
-                            modified: meta.modified().unwrap(),
-                            accessed: meta.accessed().unwrap()
-                        }))
-                    }
-                }
-            });
+    fn load_entry(&self, path: &Path) -> Result<Option<Entry>> {
+        if path.parent() != Some(self.path.as_path()) {
+            return Ok(None);
+        }
 
-        let mut directories = vec![];
-        let mut files = vec![];
-        for entry in entries {
-            match entry {
-                Entry::Dir(entry) => directories.push(entry),
-                Entry::File(entry) => files.push(entry)
+        let mut meta = fs::symlink_metadata(path).with_path(path)?;
+        if meta.file_type().is_symlink() {
+            match self.symlinks {
+                SymlinkPolicy::Skip => return Ok(None),
+                SymlinkPolicy::Follow => meta = fs::metadata(path).with_path(path)?,
+                SymlinkPolicy::Link => {}
             }
         }
-        (directories, files)
+
+        if self.ignores.ignored(path, meta.is_dir()) {
+            return Ok(None);
+        }
+
+        let name = Self::name(path);
+        let path = path.to_path_buf();
+
+        let kind = if meta.file_type().is_symlink() {
+            FileKind::Link
+        } else if meta.is_file() {
+            FileKind::Regular
+        } else {
+            FileKind::Special
+        };
+
+        if meta.is_dir() {
+            Ok(Some(Entry::Dir(DirEntry { name, path })))
+        } else {
+            Ok(Some(Entry::File(FileEntry {
+                kind,
+                size: meta.len(),
+                created: meta.created().with_path(&path)?,
+                modified: meta.modified().with_path(&path)?,
+                accessed: meta.accessed().with_path(&path)?,
+                mime: None,
+
+                name,
+                path
+            })))
+        }
+    }
+
+    fn name(path: &Path) -> OsString {
+        path.file_name()
+            .map(|name| name.to_owned())
+            .unwrap_or_default()
     }
 }
 
 enum Entry {
     Dir(DirEntry),
     File(FileEntry)
-}
\ No newline at end of file
+}
@@ -2,35 +2,321 @@ pub mod id;
 pub mod tag;
 pub mod entry;
 pub mod location;
+pub mod ignores;
+pub mod computed;
 
 use crate::database::Database;
-use crate::index::Index;
-use crate::{DATA_NAME, INDEX_NAME};
+use crate::error::{Error, Result, WithPath};
+use crate::config::{Config, Names, Xattrs};
+use crate::utils;
+use crate::xattrs;
+use crate::index::{Index, MigrationPolicy};
+use crate::index::verify::Verification;
+use crate::volume::{self, Volume, Registry, Recognition};
 
 use location::Location;
+use ignores::Ignores;
+use entry::{SymlinkPolicy, FileKind};
+use tag::Tag;
 
-use std::path::PathBuf;
+use id::Id;
 
+use std::path::{Path, PathBuf, Component};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+
+/// A root with its index, database and current location.
 pub struct Model {
+    pub root: PathBuf,
     pub index: Index,
     pub database: Database,
     pub location: Location,
+
+    volume: Option<Volume>,
+    policy: MigrationPolicy,
+    symlinks: SymlinkPolicy,
+    //ignore patterns of the configuration
+    patterns: Vec<String>,
+    xattrs: Xattrs,
+    names: Names,
 }
 
 impl Model {
-    pub fn new(root: PathBuf) -> Self {
-        let mut index_dir = root.clone();
-        let mut data_dir = root.clone();
-        index_dir.push(INDEX_NAME.to_owned());
-        data_dir.push(DATA_NAME.to_owned());
+    /// Opens `root`; without a central database in `config`,
+    /// the database is kept inside the root.
+    pub fn new(root: PathBuf, policy: MigrationPolicy, config: &Config) -> Result<Self> {
+        Self::open(root, policy, config, true)
+    }
+
+    /// Opens `root` like `new`, but neither marks the root as a collection
+    /// nor registers it, so it can be used on read-only media.
+    pub fn read_only(root: PathBuf, policy: MigrationPolicy, config: &Config) -> Result<Self> {
+        Self::open(root, policy, config, false)
+    }
+
+    fn open(root: PathBuf, policy: MigrationPolicy, config: &Config, writable: bool) -> Result<Self> {
+        let index_dir = root.join(&config.names.index);
+
+        let data_dir = match &config.database {
+            Some(database) => {
+                fs::create_dir_all(database).with_path(database)?;
+                Some(database.canonicalize().with_path(database)?)
+            },
+            None => None
+        };
+        let shared = data_dir.is_some();
+        let data_dir = data_dir.unwrap_or_else(|| root.join(&config.names.data));
+
+        //the index is only a cache of ids, a damaged one is rebuilt
+        let mut index = Index::load(index_dir.clone())
+            .unwrap_or_else(|error| {
+                println!("WARNING: {}, the index is rebuilt", error);
+                Index::new(index_dir)
+            })
+            .with_hashing(config.hashing);
+        let mut database = Database::new(data_dir)?;
+        if config.xattrs.write_through {
+            database = database.with_write_through();
+        }
+
+        let symlinks = config.symlinks;
+        let patterns = config.ignore.clone();
+        let xattrs = config.xattrs;
+        let names = config.names.clone();
+        let location = Location::root(root.clone(), &names, &patterns, symlinks, &mut index)?;
+        let root = location.path().to_path_buf();
+        let volume = if writable {
+            Self::recognize(&root, &names.volume,
+                if shared { Some(database.path()) } else { None })
+        } else {
+            Volume::read(&root, &names.volume)
+                .map_err(|error| println!("WARNING: {}", error))
+                .ok()
+                .flatten()
+        };
+
+        let mut model = Model {
+            root, index, database, location,
+            volume, policy, symlinks, patterns, xattrs, names
+        };
+        for error in model.migrate_rehashed() {
+            println!("WARNING: {}", error);
+        }
+        Ok(model)
+    }
+
+    /// Identity of the collection, if the root could be given one.
+    pub fn volume(&self) -> Option<&Volume> {
+        self.volume.as_ref()
+    }
+
+    /// What is done with tags of files which content has changed.
+    pub fn policy(&self) -> MigrationPolicy {
+        self.policy
+    }
+
+    /// Names of the service files in the root.
+    pub fn names(&self) -> &Names {
+        &self.names
+    }
+
+    /// Copies tags of ids computed by an older version of hashing to the
+    /// current ids; old ids keep them for other roots sharing the database.
+    pub fn migrate_rehashed(&mut self) -> Vec<Error> {
+        let mut errors = vec![];
+        for change in self.index.take_rehashed() {
+            if let Err(error) = self.database.migrate(change.old, change.new, true) {
+                errors.push(error);
+            }
+        }
+        errors
+    }
+
+    /// Indexes the whole tree, not only the current location.
+    pub fn scan(&mut self) -> Vec<Error> {
+        let mut errors = vec![];
+        let ignores = Ignores::root(&self.root, &self.names, &self.patterns, &mut errors);
+        self.index.scan(&self.root, &ignores, self.symlinks, &mut errors);
+        //records of directories which couldn't be listed are kept
+        if unlisted(&errors) == 0 {
+            self.index.prune();
+        }
+        errors.extend(self.migrate_rehashed());
+        errors
+    }
+
+    /// Imports tags of `user.xdg.tags` if configured, the whole tree must be scanned;
+    /// the database changes, so it is never done by the scan itself.
+    pub fn import_xattrs(&mut self) -> Vec<Error> {
+        let mut errors = vec![];
+        if self.xattrs.import {
+            let export = self.xattrs.write_through;
+            let sync = xattrs::sync(self, export, &mut errors);
+            println!("\t{} tags imported from attributes, {} files updated",
+                sync.imported, sync.exported);
+        }
+        errors
+    }
+
+    /// Id of any file in the root, computed if it hasn't been yet or the file
+    /// has been changed since; directories and special files have no id.
+    pub fn id_of(&mut self, path: &Path) -> Result<Option<Id>> {
+        //nothing watches the files, so the index is checked against them every time
+        let mut meta = fs::symlink_metadata(path).with_path(path)?;
+        let kind = if meta.file_type().is_symlink() {
+            match self.symlinks {
+                SymlinkPolicy::Skip => return Ok(None),
+                SymlinkPolicy::Link => FileKind::Link,
+                SymlinkPolicy::Follow => {
+                    meta = fs::metadata(path).with_path(path)?;
+                    if meta.is_file() { FileKind::Regular } else { FileKind::Special }
+                }
+            }
+        } else if meta.is_file() {
+            FileKind::Regular
+        } else {
+            FileKind::Special
+        };
+
+        if meta.is_dir() {
+            return Ok(None);
+        }
 
-        let ignores = vec![INDEX_NAME.to_string(), DATA_NAME.to_string()];
-            //todo: remove clone()
+        self.index.provide(path, kind)?;
+        for error in self.migrate_rehashed() {
+            println!("WARNING: {}", error);
+        }
+        Ok(self.index.id(path))
+    }
+
+    /// Absolute path of `path` given relative to the root or absolute;
+    /// paths leading outside of the root are rejected.
+    /// Links are not resolved since they can be tagged themselves.
+    pub fn resolve(&self, path: &Path) -> Result<PathBuf> {
+        let path = self.root.join(path);
+        let parent = path.components()
+            .any(|component| component == Component::ParentDir);
 
-        let mut index = Index::new(index_dir);
-        let database = Database::new(data_dir);
+        if !parent && path.starts_with(&self.root) {
+            Ok(path)
+        } else {
+            Err(Error::OutsideRoot { path })
+        }
+    }
 
-        let location = Location::root(root, ignores, &mut index);
-        Model { index, database, location }
+    /// Indexed path of a file with `id`, if there is one.
+    pub fn path_of(&self, id: Id) -> Option<PathBuf> {
+        self.index.path(id).cloned()
     }
-}
\ No newline at end of file
+
+    /// Indexed paths of files having all of `tags`, stored or computed, sorted;
+    /// the tree must be scanned to find files outside of the current location.
+    pub fn query(&self, tags: &[Tag]) -> Vec<PathBuf> {
+        let (paths, ids): (Vec<&PathBuf>, Vec<Option<Id>>) = self.index.entries()
+            .map(|(path, id)| (path, Some(*id)))
+            .unzip();
+
+        //files are looked at only when computed tags are asked for
+        let computed: Vec<BTreeSet<Tag>> = if tags.iter().any(|tag| computed::is_computed(tag)) {
+            paths.iter()
+                .map(|path| fs::metadata(path)
+                    .and_then(|meta| Ok(computed::tags(
+                        path.file_name().unwrap_or_default(), meta.len(), meta.modified()?,
+                        self.index.mime(path))))
+                    .unwrap_or_default())
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let filter = computed::filter(&self.database, &ids, &computed, tags.iter());
+        let mut paths: Vec<PathBuf> = utils::apply_filter(paths.into_iter(), filter.into_iter())
+            .cloned()
+            .collect();
+
+        paths.sort();
+        paths
+    }
+
+    /// Re-hashes the recorded files which seem unchanged; the records are loaded
+    /// as stored, since opening the model has provided the files of the root again.
+    pub fn verify(&self) -> Result<Verification> {
+        let index = Index::load(self.root.join(&self.names.index))?;
+        Ok(index.verify())
+    }
+
+    //ids of this root and of all other roots sharing its database;
+    //ids of an unmounted root or of a root which couldn't be scanned completely
+    //are unknown, so nothing can be judged orphaned
+    pub fn known_ids(&self) -> Result<HashSet<Id>> {
+        let mut known: HashSet<Id> = self.index.ids().cloned().collect();
+
+        let registry = Registry::load()?;
+        let others = registry.sharing(self.database.path())
+            .filter(|(_, registration)| registration.root != self.root);
+
+        for (id, registration) in others {
+            if !volume::mounted(id, registration, &self.names.volume) {
+                return Err(Error::Unmounted { path: registration.root.clone() });
+            }
+
+            //the persisted index holds only visited locations, so the other root is scanned
+            let root = &registration.root;
+            let index_dir = root.join(&self.names.index);
+            let mut index = Index::load(index_dir.clone())
+                .unwrap_or_else(|_| Index::new(index_dir))
+                .with_hashing(self.index.hashing());
+            let mut errors = vec![];
+            let ignores = Ignores::root(root, &self.names, &self.patterns, &mut errors);
+            index.scan(root, &ignores, self.symlinks, &mut errors);
+
+            for error in &errors {
+                println!("WARNING: {}", error);
+            }
+            match unlisted(&errors) {
+                0 => index.prune(),
+                count => return Err(Error::Unscanned { count })
+            }
+
+            //the index isn't saved, the other root migrates its rehashed ids itself
+            known.extend(index.ids().cloned());
+            known.extend(index.take_rehashed().into_iter().map(|change| change.old));
+        }
+
+        Ok(known)
+    }
+
+    //a collection without a volume identity (e.g. on read-only media) is still usable
+    fn recognize(root: &Path, name: &str, database: Option<&Path>) -> Option<Volume> {
+        let volume = Volume::identify(root, name)
+            .map_err(|error| println!("WARNING: {}", error))
+            .ok()?;
+
+        let registry = Registry::load()
+            .map(|mut registry| {
+                match registry.recognize(&volume, database) {
+                    Recognition::Moved(previous) =>
+                        println!("Collection {} has been mounted at {:?} before",
+                            volume.id, previous),
+                    Recognition::New =>
+                        println!("Collection {} is seen for the first time", volume.id),
+                    Recognition::Known => {}
+                }
+                registry
+            })
+            .and_then(|registry| registry.save());
+
+        if let Err(error) = registry {
+            println!("WARNING: {}", error);
+        }
+        Some(volume)
+    }
+}
+
+/// Number of directories which couldn't be listed while scanning;
+/// only then some files of the tree may be unknown.
+pub fn unlisted(errors: &[Error]) -> usize {
+    errors.iter()
+        .filter(|error| matches!(error, Error::Unlisted { .. }))
+        .count()
+}
@@ -0,0 +1,135 @@
+//! XMP sidecars of tagged files, `photo.jpg.xmp` for `photo.jpg`, with tags
+//! as `dc:subject`. Existing sidecars are merged with: subjects and everything
+//! else written by other tools are kept. Exported subjects are also recorded
+//! as `ark:exported`, so they are removed once the file isn't tagged with them.
+//!
+//! Sidecars of existing files, also `photo.xmp` as written by darktable
+//! or Lightroom, are never listed nor indexed.
+
+use crate::model::{Model, tag::Tag};
+use crate::xmp::{self, Property};
+use crate::error::{Error, Result, WithPath};
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+pub const EXTENSION: &str = "xmp";
+
+//subjects written by ark-tagger
+const EXPORTED: Property = Property {
+    namespace: "http://ns.ark-tagger/1.0/",
+    prefix: "ark",
+    name: "exported"
+};
+
+pub fn path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".");
+    name.push(EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Whether `path` is a sidecar of an existing file.
+pub fn is_sidecar(path: &Path) -> bool {
+    if path.extension().map(|extension| extension != EXTENSION).unwrap_or(true) {
+        return false;
+    }
+    if path.with_extension("").is_file() {
+        return true;
+    }
+
+    //`photo.xmp` can belong to a file with any extension
+    let (directory, stem) = match (path.parent(), path.file_stem()) {
+        (Some(directory), Some(stem)) => (directory, stem),
+        _ => return false
+    };
+    fs::read_dir(directory)
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .any(|file| file.file_stem() == Some(stem) && file != path && file.is_file()))
+        .unwrap_or(false)
+}
+
+/// Subjects of the sidecar of `file`, either `photo.jpg.xmp` or `photo.xmp`,
+/// `None` if there is no sidecar.
+pub fn read(file: &Path) -> Result<Option<BTreeSet<String>>> {
+    for sidecar in [path(file), file.with_extension(EXTENSION)].iter() {
+        if let Some(xml) = load(sidecar)? {
+            return bag(&xml, &xmp::SUBJECT)
+                .map(Some)
+                .map_err(|message| Error::Metadata { path: sidecar.clone(), message });
+        }
+    }
+    Ok(None)
+}
+
+/// Replaces subjects exported before with `tags` in the sidecar of `file`,
+/// returns `false` if it is up to date already.
+pub fn write(file: &Path, tags: &BTreeSet<Tag>) -> Result<bool> {
+    let sidecar = path(file);
+    let xml = match load(&sidecar)? {
+        Some(xml) => Some(xml),
+        None if tags.is_empty() => return Ok(false),
+        None => None
+    };
+
+    let metadata = |message: String| Error::Metadata { path: sidecar.clone(), message };
+    let (existing, exported) = match &xml {
+        Some(xml) => (
+            bag(xml, &xmp::SUBJECT).map_err(metadata)?,
+            bag(xml, &EXPORTED).map_err(metadata)?),
+        None => (BTreeSet::new(), BTreeSet::new())
+    };
+
+    //subjects of other tools stay, even if they are tags too
+    let foreign: BTreeSet<String> = existing.difference(&exported).cloned().collect();
+    let subjects: BTreeSet<String> = foreign.union(tags).cloned().collect();
+    let exporting: BTreeSet<String> = tags.difference(&foreign).cloned().collect();
+    if subjects == existing && exporting == exported {
+        return Ok(false);
+    }
+
+    let xml = xmp::with_subjects(xml.as_deref(), &subjects)
+        .and_then(|xml| xmp::with_items(Some(&xml), &EXPORTED, &exporting))
+        .map_err(metadata)?;
+
+    fs::write(&sidecar, xml).with_path(&sidecar)?;
+    Ok(true)
+}
+
+/// Writes sidecars of all indexed files which are tagged or have been exported
+/// before, returns how many have been written; the whole tree must be scanned.
+pub fn export(model: &Model, errors: &mut Vec<Error>) -> usize {
+    let mut written = 0;
+
+    for (path, id) in model.index.entries() {
+        let tags: BTreeSet<Tag> = model.database.tags_of(*id).cloned().collect();
+        if !path.is_file() {
+            continue;
+        }
+
+        match write(path, &tags) {
+            Ok(true) => written += 1,
+            Ok(false) => {},
+            Err(error) => errors.push(error)
+        }
+    }
+    written
+}
+
+fn load(sidecar: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(sidecar) {
+        Ok(xml) => Ok(Some(xml)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error).with_path(sidecar)
+    }
+}
+
+fn bag(xml: &str, property: &Property) -> std::result::Result<BTreeSet<String>, String> {
+    xmp::items(xml, property)
+        .map(|items| items.into_iter().collect())
+        .map_err(|error| error.to_string())
+}
@@ -0,0 +1,550 @@
+//! Tags mounted as directories with FUSE: `cats/dogs/` lists files tagged
+//! with both "cats" and "dogs" as symbolic links, and subdirectories
+//! for the tags which narrow the selection further.
+//!
+//! * `ln -s <file> cats/dogs/` and `ln cats/x dogs/` tag the file with every tag of the path
+//! * `cp <file> cats/` tags a file of the root having the same content
+//! * `rm cats/dogs/x` removes the innermost tag, "dogs"
+//! * `mv cats/x dogs/` replaces "cats" with "dogs"
+//! * `mkdir new` creates a tag, it is kept until unmounting unless anything is tagged
+//! * `rmdir new` removes a tag which has no files
+//!
+//! While browsing, the tree is scanned and the database is loaded again
+//! every few seconds. When a daemon serves the root, tagging goes through it.
+
+use crate::model::{Model, id::Id, tag::Tag};
+use crate::farm;
+use crate::file;
+use crate::utils;
+use crate::xattrs;
+use crate::ipc::{self, Client};
+use crate::error::{Error, Result, WithPath};
+
+use fuse::{
+    Filesystem, Request, FileAttr, FileType,
+    ReplyEntry, ReplyAttr, ReplyData, ReplyDirectory,
+    ReplyEmpty, ReplyCreate, ReplyWrite,
+};
+use libc::{ENOENT, EPERM, ENOTEMPTY, EIO, EEXIST};
+use time::Timespec;
+
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{DirBuilderExt, FileExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+use std::iter;
+
+const ROOT: u64 = 1;
+
+//the view changes whenever tags change, so nothing is cached by the kernel
+const TTL: Timespec = Timespec { sec: 0, nsec: 0 };
+
+//files can be changed by anyone, so the view is refreshed at most this often
+const REFRESH: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Node {
+    //tags of the path, the innermost is the last
+    Directory(Vec<Tag>),
+    //the same path in different directories is the same inode,
+    //copies of a file are different links
+    File(Id, PathBuf),
+    //content being copied in, by the file handle
+    Copy(u64),
+}
+
+struct Incoming {
+    file: File,
+    path: PathBuf,
+    selection: Vec<Tag>,
+}
+
+pub struct TagFs {
+    model: Model,
+    //the daemon serving the root, if it is running
+    client: Option<Client>,
+    refreshed: Instant,
+    pending: BTreeSet<Tag>,
+    nodes: Vec<Node>,
+    inodes: HashMap<Node, u64>,
+    copies: HashMap<u64, Incoming>,
+    //copies are staged in a directory only the user can access
+    staging: PathBuf,
+    handles: u64,
+    mounted: Timespec,
+    uid: u32,
+    gid: u32,
+}
+
+/// Mounts the tags of `model` at `mountpoint` until unmounted;
+/// the whole tree must be scanned.
+pub fn mount(model: Model, mountpoint: &Path) -> Result<()> {
+    let client = Client::connect(&ipc::socket_path(&model.root)).ok();
+    if client.is_some() {
+        println!("Tagging through the daemon serving {:?}", model.root);
+    }
+
+    let temp = env::temp_dir();
+    let staging = temp.join(format!("ark-tagger-{}", utils::random_hex(8).with_path(&temp)?));
+    fs::DirBuilder::new().mode(0o700).create(&staging).with_path(&staging)?;
+
+    let filesystem = TagFs {
+        model,
+        client,
+        refreshed: Instant::now(),
+        pending: BTreeSet::new(),
+        nodes: vec![Node::Directory(vec![])],
+        inodes: iter::once((Node::Directory(vec![]), ROOT)).collect(),
+        copies: HashMap::new(),
+        staging: staging.clone(),
+        handles: 0,
+        mounted: time::get_time(),
+        uid: unsafe { libc::getuid() },
+        gid: unsafe { libc::getgid() },
+    };
+
+    let options = [OsStr::new("-o"), OsStr::new("fsname=ark-tagger")];
+    let result = fuse::mount(filesystem, &mountpoint, &options).with_path(mountpoint);
+
+    if let Err(error) = fs::remove_dir_all(&staging) {
+        println!("WARNING: {:?}: {}", staging, error);
+    }
+    result
+}
+
+impl TagFs {
+    fn node(&self, ino: u64) -> Option<&Node> {
+        self.nodes.get((ino - 1) as usize)
+    }
+
+    fn inode(&mut self, node: Node) -> u64 {
+        if let Some(ino) = self.inodes.get(&node) {
+            return *ino;
+        }
+
+        self.nodes.push(node.clone());
+        let ino = self.nodes.len() as u64;
+        self.inodes.insert(node, ino);
+        ino
+    }
+
+    fn selection(&self, ino: u64) -> Option<Vec<Tag>> {
+        match self.node(ino) {
+            Some(Node::Directory(tags)) => Some(tags.clone()),
+            _ => None
+        }
+    }
+
+    //indexed files having all tags of the selection, sorted by path
+    fn matches(&self, selection: &[Tag]) -> Vec<(PathBuf, Id)> {
+        let id_by_path: HashMap<&PathBuf, Id> = self.model.index.entries()
+            .map(|(path, id)| (path, *id))
+            .collect();
+
+        self.model.query(selection).into_iter()
+            .filter_map(|path| {
+                let id = *id_by_path.get(&path)?;
+                Some((path, id))
+            })
+            .collect()
+    }
+
+    //links are named as in the symlink farm
+    fn files(&self, selection: &[Tag]) -> BTreeMap<String, Node> {
+        let mut links = BTreeMap::new();
+        let mut files = BTreeMap::new();
+        for (path, id) in self.matches(selection) {
+            let name = farm::link_name(&links, &path, id);
+            links.insert(name.clone(), path.clone());
+            files.insert(name, Node::File(id, path));
+        }
+        files
+    }
+
+    //tags which narrow the selection down, and tags created with `mkdir`
+    fn subtags(&self, selection: &[Tag]) -> BTreeSet<Tag> {
+        let ids = self.matches(selection).into_iter().map(|(_, id)| id);
+
+        let mut tags: BTreeSet<Tag> = self.model.database.sieved_tags(ids)
+            .filter(|tag| tag.highlighted)
+            .map(|tag| tag.tag.clone())
+            .collect();
+
+        tags.extend(self.pending.iter().cloned());
+        tags.retain(|tag| !selection.contains(tag));
+        tags
+    }
+
+    fn entry(&mut self, parent: u64, name: &OsStr) -> Option<Node> {
+        let selection = self.selection(parent)?;
+        let name = name.to_string_lossy().into_owned();
+
+        if self.subtags(&selection).contains(&name) {
+            let mut tags = selection;
+            tags.push(name);
+            return Some(Node::Directory(tags));
+        }
+
+        self.files(&selection).remove(&name)
+    }
+
+    fn attributes(&self, ino: u64) -> Option<FileAttr> {
+        let (kind, size, perm) = match self.node(ino)? {
+            Node::Directory(_) => (FileType::Directory, 0, 0o755),
+            Node::File(_, target) => (FileType::Symlink, target.as_os_str().len() as u64, 0o777),
+            Node::Copy(handle) => {
+                let size = self.copies.get(handle)?.file.metadata().ok()?.len();
+                (FileType::RegularFile, size, 0o644)
+            }
+        };
+
+        Some(FileAttr {
+            ino,
+            size,
+            blocks: (size + 511) / 512,
+            atime: self.mounted,
+            mtime: self.mounted,
+            ctime: self.mounted,
+            crtime: self.mounted,
+            kind,
+            perm,
+            nlink: 1,
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            flags: 0,
+        })
+    }
+
+    fn reply_entry(&mut self, node: Node, reply: ReplyEntry) {
+        let ino = self.inode(node);
+        match self.attributes(ino) {
+            Some(attributes) => reply.entry(&TTL, &attributes, 0),
+            None => reply.error(ENOENT)
+        }
+    }
+
+    //the scan is cheap for unchanged files, their ids are cached
+    fn refresh(&mut self) {
+        if self.refreshed.elapsed() < REFRESH {
+            return;
+        }
+        self.refreshed = Instant::now();
+
+        for error in self.model.scan() {
+            println!("WARNING: {}", error);
+        }
+        if let Err(error) = self.model.index.save() {
+            println!("WARNING: {}", error);
+        }
+        if let Err(error) = self.model.database.reload() {
+            println!("WARNING: {}", error);
+        }
+    }
+
+    fn tag(&mut self, id: Id, path: &Path, tags: &[Tag]) -> Result<()> {
+        for tag in tags {
+            match &mut self.client {
+                Some(client) => client.tag(vec![path.to_path_buf()], tag)?,
+                None => { self.model.database.insert(iter::once(id), tag)?; }
+            }
+            self.pending.remove(tag);
+        }
+        self.save();
+        Ok(())
+    }
+
+    fn untag(&mut self, id: Id, path: &Path, tag: &Tag) -> Result<()> {
+        match &mut self.client {
+            Some(client) => client.untag(vec![path.to_path_buf()], tag)?,
+            None => { self.model.database.remove(iter::once(id), tag)?; }
+        }
+        self.save();
+        Ok(())
+    }
+
+    fn save(&mut self) {
+        if let Err(error) = self.model.index.save() {
+            println!("WARNING: {}", error);
+        }
+        //the daemon has written the database and the attributes
+        if self.client.is_some() {
+            if let Err(error) = self.model.database.reload() {
+                println!("WARNING: {}", error);
+            }
+            return;
+        }
+
+        for error in self.model.database.take_errors() {
+            println!("WARNING: {}", error);
+        }
+        for error in xattrs::write_through(&mut self.model) {
+            println!("WARNING: {}", error);
+        }
+    }
+
+    //content is identified the same way as files in the root
+    fn identify(&mut self, copy: Incoming) -> Result<Option<(Id, PathBuf)>> {
+        drop(copy.file);
+        let id = file::id(&copy.path);
+        fs::remove_file(&copy.path).with_path(&copy.path)?;
+
+        let id = id?;
+        Ok(self.model.index.path(id).map(|path| (id, path.clone())))
+    }
+}
+
+fn errno(error: &Error) -> i32 {
+    println!("WARNING: {}", error);
+    match error {
+        Error::Io { source, .. } => source.raw_os_error().unwrap_or(EIO),
+        Error::OutsideRoot { .. } => EPERM,
+        _ => EIO
+    }
+}
+
+impl Filesystem for TagFs {
+    fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        self.refresh();
+        match self.entry(parent, name) {
+            Some(node) => self.reply_entry(node, reply),
+            None => reply.error(ENOENT)
+        }
+    }
+
+    fn getattr(&mut self, _req: &Request, ino: u64, reply: ReplyAttr) {
+        match self.attributes(ino) {
+            Some(attributes) => reply.attr(&TTL, &attributes),
+            None => reply.error(ENOENT)
+        }
+    }
+
+    //changing permissions or times is accepted and ignored, e.g. for `cp -p`
+    fn setattr(&mut self, req: &Request, ino: u64, _mode: Option<u32>,
+               _uid: Option<u32>, _gid: Option<u32>, _size: Option<u64>,
+               _atime: Option<Timespec>, _mtime: Option<Timespec>, _fh: Option<u64>,
+               _crtime: Option<Timespec>, _chgtime: Option<Timespec>,
+               _bkuptime: Option<Timespec>, _flags: Option<u32>, reply: ReplyAttr) {
+        self.getattr(req, ino, reply)
+    }
+
+    fn readlink(&mut self, _req: &Request, ino: u64, reply: ReplyData) {
+        match self.node(ino) {
+            Some(Node::File(_, target)) => reply.data(target.as_os_str().as_bytes()),
+            _ => reply.error(ENOENT)
+        }
+    }
+
+    fn readdir(&mut self, _req: &Request, ino: u64, _fh: u64, offset: i64, mut reply: ReplyDirectory) {
+        //listing is continued at an offset, the entries mustn't change in between
+        if offset == 0 {
+            self.refresh();
+        }
+        let selection = match self.selection(ino) {
+            Some(selection) => selection,
+            None => return reply.error(ENOENT)
+        };
+
+        let mut entries: Vec<(u64, FileType, OsString)> = vec![
+            (ino, FileType::Directory, ".".into()),
+            (ino, FileType::Directory, "..".into()),
+        ];
+
+        for tag in self.subtags(&selection) {
+            let mut tags = selection.clone();
+            tags.push(tag.clone());
+            entries.push((self.inode(Node::Directory(tags)), FileType::Directory, tag.into()));
+        }
+
+        for (name, file) in self.files(&selection) {
+            entries.push((self.inode(file), FileType::Symlink, name.into()));
+        }
+
+        for (i, (ino, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(ino, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+
+    fn mkdir(&mut self, _req: &Request, parent: u64, name: &OsStr, _mode: u32, reply: ReplyEntry) {
+        let mut tags = match self.selection(parent) {
+            Some(selection) => selection,
+            None => return reply.error(ENOENT)
+        };
+        if self.entry(parent, name).is_some() {
+            return reply.error(EEXIST);
+        }
+
+        let tag = name.to_string_lossy().into_owned();
+        self.pending.insert(tag.clone());
+        tags.push(tag);
+        self.reply_entry(Node::Directory(tags), reply);
+    }
+
+    fn rmdir(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let tags = match self.entry(parent, name) {
+            Some(Node::Directory(tags)) => tags,
+            _ => return reply.error(ENOENT)
+        };
+
+        if !self.files(&tags).is_empty() {
+            return reply.error(ENOTEMPTY);
+        }
+        self.pending.remove(tags.last().unwrap());
+        reply.ok();
+    }
+
+    fn symlink(&mut self, _req: &Request, parent: u64, _name: &OsStr, link: &Path, reply: ReplyEntry) {
+        let selection = match self.selection(parent) {
+            Some(selection) if !selection.is_empty() => selection,
+            Some(_) => return reply.error(EPERM),
+            None => return reply.error(ENOENT)
+        };
+
+        let result = self.model.resolve(link)
+            .and_then(|path| Ok(self.model.id_of(&path)?.map(|id| (id, path))));
+        match result {
+            Ok(Some((id, path))) => match self.tag(id, &path, &selection) {
+                Ok(()) => self.reply_entry(Node::File(id, path), reply),
+                Err(error) => reply.error(errno(&error))
+            },
+            Ok(None) => reply.error(EPERM),
+            Err(error) => reply.error(errno(&error))
+        }
+    }
+
+    fn link(&mut self, _req: &Request, ino: u64, newparent: u64, _newname: &OsStr, reply: ReplyEntry) {
+        let (id, path) = match self.node(ino) {
+            Some(Node::File(id, path)) => (*id, path.clone()),
+            _ => return reply.error(EPERM)
+        };
+        let selection = match self.selection(newparent) {
+            Some(selection) if !selection.is_empty() => selection,
+            Some(_) => return reply.error(EPERM),
+            None => return reply.error(ENOENT)
+        };
+
+        match self.tag(id, &path, &selection) {
+            Ok(()) => self.reply_entry(Node::File(id, path), reply),
+            Err(error) => reply.error(errno(&error))
+        }
+    }
+
+    fn unlink(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let selection = self.selection(parent).unwrap_or_default();
+        let tag = match selection.last() {
+            Some(tag) => tag.clone(),
+            None => return reply.error(EPERM)
+        };
+
+        match self.entry(parent, name) {
+            Some(Node::File(id, path)) => match self.untag(id, &path, &tag) {
+                Ok(()) => reply.ok(),
+                Err(error) => reply.error(errno(&error))
+            },
+            Some(_) => reply.error(EPERM),
+            None => reply.error(ENOENT)
+        }
+    }
+
+    //only files can be moved, directories are tags themselves
+    fn rename(&mut self, _req: &Request, parent: u64, name: &OsStr,
+              newparent: u64, _newname: &OsStr, reply: ReplyEmpty) {
+        let (id, path) = match self.entry(parent, name) {
+            Some(Node::File(id, path)) => (id, path),
+            Some(_) => return reply.error(EPERM),
+            None => return reply.error(ENOENT)
+        };
+
+        let old = self.selection(parent).unwrap_or_default();
+        let new = match self.selection(newparent) {
+            Some(new) => new,
+            None => return reply.error(ENOENT)
+        };
+        if old.is_empty() || new.is_empty() {
+            return reply.error(EPERM);
+        }
+
+        let result = self.tag(id, &path, &new)
+            .and_then(|_| match old.last() {
+                Some(tag) if !new.contains(tag) => self.untag(id, &path, tag),
+                _ => Ok(())
+            });
+        match result {
+            Ok(()) => reply.ok(),
+            Err(error) => reply.error(errno(&error))
+        }
+    }
+
+    //copied content is collected into a temporary file and identified when released
+    fn create(&mut self, _req: &Request, parent: u64, _name: &OsStr,
+              _mode: u32, flags: u32, reply: ReplyCreate) {
+        let selection = match self.selection(parent) {
+            Some(selection) if !selection.is_empty() => selection,
+            Some(_) => return reply.error(EPERM),
+            None => return reply.error(ENOENT)
+        };
+
+        self.handles += 1;
+        let handle = self.handles;
+        let path = self.staging.join(format!("{}.copy", handle));
+
+        //nothing is followed or overwritten at the path
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path);
+        let file = match file {
+            Ok(file) => file,
+            Err(error) => return reply.error(error.raw_os_error().unwrap_or(EIO))
+        };
+        self.copies.insert(handle, Incoming { file, path, selection });
+
+        let ino = self.inode(Node::Copy(handle));
+        match self.attributes(ino) {
+            Some(attributes) => reply.created(&TTL, &attributes, 0, handle, flags),
+            None => reply.error(EIO)
+        }
+    }
+
+    fn write(&mut self, _req: &Request, _ino: u64, fh: u64, offset: i64,
+             data: &[u8], _flags: u32, reply: ReplyWrite) {
+        let copy = match self.copies.get(&fh) {
+            Some(copy) => copy,
+            None => return reply.error(EPERM)
+        };
+
+        match copy.file.write_all_at(data, offset as u64) {
+            Ok(()) => reply.written(data.len() as u32),
+            Err(error) => reply.error(error.raw_os_error().unwrap_or(EIO))
+        }
+    }
+
+    fn release(&mut self, _req: &Request, _ino: u64, fh: u64, _flags: u32,
+               _lock_owner: u64, _flush: bool, reply: ReplyEmpty) {
+        let copy = match self.copies.remove(&fh) {
+            Some(copy) => copy,
+            None => return reply.ok()
+        };
+
+        let selection = copy.selection.clone();
+        match self.identify(copy) {
+            Ok(Some((id, path))) => match self.tag(id, &path, &selection) {
+                Ok(()) => reply.ok(),
+                Err(error) => reply.error(errno(&error))
+            },
+            Ok(None) => {
+                println!("WARNING: the copied content is not in the root, nothing is tagged");
+                reply.ok()
+            },
+            Err(error) => reply.error(errno(&error))
+        }
+    }
+}
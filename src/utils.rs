@@ -1,5 +1,16 @@
 pub type Filter = Vec<bool>;
 
+//debugging output, printed only when built with the `trace` feature
+#[doc(hidden)]
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        if cfg!(feature = "trace") {
+            println!($($arg)*);
+        }
+    }
+}
+
 pub fn apply_filter<I,E,F>(elements: E, filter: F) -> impl Iterator<Item = I>
     where E: Iterator<Item = I>,
           F: Iterator<Item = bool> {
@@ -16,6 +27,19 @@ where F: FnOnce() -> T {
     let start = Instant::now();
     let result = f();
 
-    println!("[timing] {}: {}ns", name, start.elapsed().as_nanos());
+    crate::trace!("[timing] {}: {}ns", name, start.elapsed().as_nanos());
     result
-}
\ No newline at end of file
+}
+
+//e.g. for identifiers and secrets
+pub fn random_hex(bytes: usize) -> std::io::Result<String> {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut buffer = vec![0u8; bytes];
+    File::open("/dev/urandom")?.read_exact(&mut buffer)?;
+
+    Ok(buffer.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect())
+}
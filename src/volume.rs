@@ -0,0 +1,155 @@
+use crate::error::{Error, Result, WithPath};
+use crate::utils;
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+
+//a collection is identified by a random id stored in its root,
+//so it can be recognized on any machine and at any mount point
+#[derive(Debug, Clone)]
+pub struct Volume {
+    pub id: String,
+    pub root: PathBuf
+}
+
+impl Volume {
+    //the marker named `name` is created if the root doesn't have one yet
+    pub fn identify(root: &Path, name: &str) -> Result<Self> {
+        if let Some(volume) = Self::read(root, name)? {
+            return Ok(volume);
+        }
+
+        let marker = root.join(name);
+        let id = utils::random_hex(16).with_path(&marker)?;
+        fs::write(&marker, format!("{}\n", id)).with_path(&marker)?;
+        println!("New collection {} in {:?}", id, root);
+
+        Ok(Volume { id, root: root.to_path_buf() })
+    }
+
+    pub fn read(root: &Path, name: &str) -> Result<Option<Self>> {
+        let marker = root.join(name);
+
+        let id = match fs::read_to_string(&marker) {
+            Ok(id) => id.trim().to_owned(),
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error).with_path(&marker)
+        };
+
+        if id.is_empty() || !id.bytes().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::UnexpectedEntry { path: marker });
+        }
+
+        Ok(Some(Volume { id, root: root.to_path_buf() }))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Recognition {
+    New,
+    Known,
+    //the collection has been mounted elsewhere before
+    Moved(PathBuf)
+}
+
+//collections seen on this machine with their last known roots
+//and central databases they use, if any
+pub struct Registry {
+    path: PathBuf,
+    roots: BTreeMap<String, Registration>
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Registration {
+    pub root: PathBuf,
+    pub database: Option<PathBuf>
+}
+
+impl Registry {
+    pub fn load() -> Result<Self> {
+        let path = dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ark-tagger")
+            .join("volumes");
+
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => String::new(),
+            Err(error) => return Err(error).with_path(&path)
+        };
+
+        //lines are `id<tab>root[<tab>database]`,
+        //older registries separated only id and root with a space
+        let roots = content.lines()
+            .filter_map(|line| {
+                let mut parts: Box<dyn Iterator<Item = &str>> = if line.contains('\t') {
+                    Box::new(line.splitn(3, '\t'))
+                } else {
+                    Box::new(line.splitn(2, ' '))
+                };
+                let id = parts.next()?;
+                let root = PathBuf::from(parts.next()?);
+                let database = parts.next().map(PathBuf::from);
+                Some((id.to_owned(), Registration { root, database }))
+            })
+            .collect();
+
+        Ok(Registry { path, roots })
+    }
+
+    //the registered database is kept unless another one is given,
+    //opening a root once without it must not unregister it
+    pub fn recognize(&mut self, volume: &Volume, database: Option<&Path>) -> Recognition {
+        let database = database.map(|database| database.to_path_buf())
+            .or_else(|| self.roots.get(&volume.id)
+                .and_then(|registration| registration.database.clone()));
+        let registration = Registration { root: volume.root.clone(), database };
+        let previous = self.roots.insert(volume.id.clone(), registration);
+
+        match previous {
+            None => Recognition::New,
+            Some(previous) if previous.root == volume.root => Recognition::Known,
+            Some(previous) => Recognition::Moved(previous.root)
+        }
+    }
+
+    pub fn roots(&self) -> impl Iterator<Item = (&String, &Registration)> {
+        self.roots.iter()
+    }
+
+    pub fn sharing<'a>(&'a self, database: &'a Path) -> impl Iterator<Item = (&'a String, &'a Registration)> {
+        self.roots.iter()
+            .filter(move |(_, registration)|
+                registration.database.as_deref() == Some(database))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_path(parent)?;
+        }
+
+        let content: String = self.roots.iter()
+            .map(|(id, registration)| {
+                let mut line = format!("{}\t{}", id, registration.root.to_string_lossy());
+                if let Some(database) = &registration.database {
+                    line.push('\t');
+                    line.push_str(&database.to_string_lossy());
+                }
+                line.push('\n');
+                line
+            })
+            .collect();
+
+        fs::write(&self.path, content).with_path(&self.path)
+    }
+}
+
+//the collection is mounted at the registered root,
+//`name` is the name of the markers
+pub fn mounted(id: &str, registration: &Registration, name: &str) -> bool {
+    Volume::read(&registration.root, name)
+        .map(|volume| volume.map(|volume| volume.id == id).unwrap_or(false))
+        .unwrap_or(false)
+}
@@ -0,0 +1,150 @@
+//! Sync with the `user.xdg.tags` extended attribute, a comma-separated
+//! list of tags written by file managers and other tools.
+//!
+//! Tags found only in the attribute are imported, tags found only in the
+//! database are exported; nothing is ever removed from either side,
+//! differences in both directions are reported as conflicts.
+
+use crate::model::{Model, id::Id, tag::Tag};
+use crate::error::{Error, Result, WithPath};
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const ATTRIBUTE: &str = "user.xdg.tags";
+
+const SEPARATOR: char = ',';
+
+#[derive(Debug, Default)]
+pub struct Synchronization {
+    pub imported: usize,
+    pub exported: usize,
+}
+
+/// Tags of the attribute, `None` if the file doesn't have it.
+pub fn read(path: &Path) -> Result<Option<BTreeSet<Tag>>> {
+    let value = match xattr::get(path, ATTRIBUTE).with_path(path)? {
+        Some(value) => value,
+        None => return Ok(None)
+    };
+
+    let tags = String::from_utf8_lossy(&value)
+        .split(SEPARATOR)
+        .map(|tag| tag.trim().to_owned())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    Ok(Some(tags))
+}
+
+/// Replaces the attribute, it is removed when there are no tags.
+pub fn write<'a, I>(path: &Path, tags: I) -> Result<()>
+    where I: Iterator<Item = &'a Tag> {
+    let value: Vec<&str> = tags.map(|tag| tag.as_str()).collect();
+
+    if value.is_empty() {
+        if xattr::get(path, ATTRIBUTE).with_path(path)?.is_some() {
+            xattr::remove(path, ATTRIBUTE).with_path(path)?;
+        }
+        Ok(())
+    } else {
+        xattr::set(path, ATTRIBUTE, value.join(",").as_bytes()).with_path(path)
+    }
+}
+
+/// Two-way sync of all indexed files, the whole tree must be scanned.
+/// With `export` disabled the attributes are only read.
+pub fn sync(model: &mut Model, export: bool, errors: &mut Vec<Error>) -> Synchronization {
+    let mut sync = Synchronization::default();
+
+    for (path, id) in files(model) {
+        let attribute = match read(&path) {
+            Ok(attribute) => attribute,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+        //tags which can't be written are not compared
+        let mut database: BTreeSet<Tag> = model.database.tags_of(id)
+            .filter(|tag| !tag.contains(SEPARATOR))
+            .cloned()
+            .collect();
+
+        if let Some(attribute) = &attribute {
+            let imported: Vec<&Tag> = attribute.difference(&database).collect();
+            let missing: Vec<&Tag> = database.difference(attribute).collect();
+
+            if !imported.is_empty() && !missing.is_empty() {
+                let message = format!("{:?} are only in the attribute, {:?} only in the database",
+                    imported, missing);
+                errors.push(Error::Xattr { path: path.clone(), message });
+            }
+
+            for tag in imported {
+                match model.database.insert(std::iter::once(id), tag) {
+                    Ok(_) => sync.imported += 1,
+                    Err(error) => errors.push(error)
+                }
+            }
+            database.extend(attribute.iter().cloned());
+        }
+
+        let written = attribute.unwrap_or_default();
+        if export && written != database {
+            let tags: BTreeSet<Tag> = model.database.tags_of(id).cloned().collect();
+            match export_tags(&path, &tags) {
+                Ok(()) => sync.exported += 1,
+                Err(error) => errors.push(error)
+            }
+        }
+    }
+
+    errors.extend(model.database.take_errors());
+    sync
+}
+
+/// Writes tags of ids changed since the previous call to their files,
+/// does nothing unless the database tracks changes with write-through.
+pub fn write_through(model: &mut Model) -> Vec<Error> {
+    let mut errors = vec![];
+
+    for id in model.database.take_modified() {
+        let tags: BTreeSet<Tag> = model.database.tags_of(id).cloned().collect();
+        for path in model.index.paths(id).filter(|path| attributable(path)) {
+            if let Err(error) = export_tags(path, &tags) {
+                errors.push(error);
+            }
+        }
+    }
+    errors
+}
+
+//the separator can't be escaped, so such tags are reported and skipped
+fn export_tags(path: &Path, tags: &BTreeSet<Tag>) -> Result<()> {
+    let (representable, unrepresentable): (Vec<&Tag>, Vec<&Tag>) = tags.iter()
+        .partition(|tag| !tag.contains(SEPARATOR));
+
+    write(path, representable.into_iter())?;
+
+    if unrepresentable.is_empty() {
+        Ok(())
+    } else {
+        let message = format!("{:?} can't be written since they contain commas", unrepresentable);
+        Err(Error::Xattr { path: path.to_path_buf(), message })
+    }
+}
+
+fn files(model: &Model) -> Vec<(PathBuf, Id)> {
+    model.index.entries()
+        .filter(|(path, _)| attributable(path))
+        .map(|(path, id)| (path.clone(), *id))
+        .collect()
+}
+
+//regular files only: symbolic links can't have attributes in the `user` namespace
+fn attributable(path: &Path) -> bool {
+    fs::symlink_metadata(path)
+        .map(|meta| meta.is_file())
+        .unwrap_or(false)
+}
@@ -0,0 +1,135 @@
+//! XMP packets, keywords are the items of the `dc:subject` bag.
+//! Other bags, e.g. of custom properties, are read and written the same way.
+
+use roxmltree::{Document, Node};
+
+use std::collections::BTreeSet;
+
+const DC: &str = "http://purl.org/dc/elements/1.1/";
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+
+/// Bag property, `prefix` is used only when the document doesn't have one.
+pub struct Property<'a> {
+    pub namespace: &'a str,
+    pub prefix: &'a str,
+    pub name: &'a str,
+}
+
+pub const SUBJECT: Property = Property { namespace: DC, prefix: "dc", name: "subject" };
+
+const START: &[u8] = b"<x:xmpmeta";
+const END: &[u8] = b"</x:xmpmeta>";
+
+/// XMP packet embedded anywhere in `bytes`, packets are designed
+/// to be found by scanning regardless of the file format.
+pub fn packet(bytes: &[u8]) -> Option<&[u8]> {
+    let start = find(bytes, START)?;
+    let end = start + find(&bytes[start..], END)? + END.len();
+    Some(&bytes[start..end])
+}
+
+/// Items of `dc:subject`, in order.
+pub fn subjects(xml: &str) -> Result<Vec<String>, roxmltree::Error> {
+    items(xml, &SUBJECT)
+}
+
+/// Items of the bag `property`, in order.
+pub fn items(xml: &str, property: &Property) -> Result<Vec<String>, roxmltree::Error> {
+    let document = Document::parse(xml)?;
+
+    let items = document.descendants()
+        .filter(|node| node.has_tag_name((property.namespace, property.name)))
+        .flat_map(|bag| bag.descendants())
+        .filter(|node| node.has_tag_name((RDF, "li")))
+        .filter_map(|item| item.text())
+        .map(|text| text.to_owned())
+        .collect();
+    Ok(items)
+}
+
+/// `xml` with items of `dc:subject` replaced by `subjects`, other properties
+/// are kept as they are; without `xml` a new document is made.
+pub fn with_subjects(xml: Option<&str>, subjects: &BTreeSet<String>) -> Result<String, String> {
+    with_items(xml, &SUBJECT, subjects)
+}
+
+/// Same as `with_subjects`, for the bag `property`.
+pub fn with_items(xml: Option<&str>, property: &Property, items: &BTreeSet<String>) -> Result<String, String> {
+    let xml = match xml {
+        Some(xml) => xml,
+        None => return Ok(format!(concat!(
+            "<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n",
+            " <rdf:RDF xmlns:rdf=\"{}\">\n",
+            "  <rdf:Description rdf:about=\"\" xmlns:{}=\"{}\">\n",
+            "{}",
+            "  </rdf:Description>\n",
+            " </rdf:RDF>\n",
+            "</x:xmpmeta>\n"), RDF, property.prefix, property.namespace,
+            bag(property, Some(property.prefix), Some("rdf"), items)))
+    };
+
+    let document = Document::parse(xml).map_err(|error| error.to_string())?;
+
+    //the existing property is replaced using the prefixes of the document
+    let existing = document.descendants()
+        .find(|node| node.has_tag_name((property.namespace, property.name)));
+    if let Some(node) = existing {
+        let replacement = bag(property, node.lookup_prefix(property.namespace),
+            node.lookup_prefix(RDF), items);
+        let range = node.range();
+        return Ok(format!("{}{}{}", &xml[..range.start], replacement.trim(), &xml[range.end..]));
+    }
+
+    //otherwise one more description is added
+    let rdf = document.descendants()
+        .find(|node| node.has_tag_name((RDF, "RDF")))
+        .ok_or_else(|| "no rdf:RDF element".to_owned())?;
+    let end = end_tag(xml, &rdf)
+        .ok_or_else(|| "empty rdf:RDF element".to_owned())?;
+
+    let rdf_prefix = rdf.lookup_prefix(RDF);
+    let description = format!(" <{} {}=\"\" xmlns:{}=\"{}\">\n{}  </{}>\n ",
+        qualified(rdf_prefix, "Description"), qualified(rdf_prefix, "about"),
+        property.prefix, property.namespace,
+        bag(property, Some(property.prefix), rdf_prefix, items),
+        qualified(rdf_prefix, "Description"));
+    Ok(format!("{}{}{}", &xml[..end], description, &xml[end..]))
+}
+
+fn bag(property: &Property, prefix: Option<&str>, rdf: Option<&str>, items: &BTreeSet<String>) -> String {
+    let items: String = items.iter()
+        .map(|item| format!("     <{}>{}</{}>\n", qualified(rdf, "li"), escape(item), qualified(rdf, "li")))
+        .collect();
+
+    format!("   <{}>\n    <{}>\n{}    </{}>\n   </{}>\n",
+        qualified(prefix, property.name), qualified(rdf, "Bag"), items,
+        qualified(rdf, "Bag"), qualified(prefix, property.name))
+}
+
+fn qualified(prefix: Option<&str>, name: &str) -> String {
+    match prefix {
+        Some(prefix) => format!("{}:{}", prefix, name),
+        None => name.to_owned()
+    }
+}
+
+//position of the closing tag of a non-empty element
+fn end_tag(xml: &str, node: &Node) -> Option<usize> {
+    let range = node.range();
+    if xml[range.clone()].ends_with("/>") {
+        return None;
+    }
+    xml[range.clone()].rfind("</").map(|i| range.start + i)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn find(bytes: &[u8], pattern: &[u8]) -> Option<usize> {
+    bytes.windows(pattern.len())
+        .position(|window| window == pattern)
+}
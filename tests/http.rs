@@ -0,0 +1,156 @@
+//requests against a server bound to an ephemeral port of localhost,
+//every test has its own root in the temporary directory
+
+use ark_tagger::{Config, Model, MigrationPolicy};
+use ark_tagger::http::Server;
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::os::unix::fs::symlink;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+
+const TOKEN: &str = "secret";
+
+struct Instance {
+    root: PathBuf,
+    address: SocketAddr,
+}
+
+impl Instance {
+    fn start(name: &str, read_only: bool) -> Self {
+        let base = env::temp_dir().join(format!("ark-tagger-http-{}", process::id()));
+        //the registry of collections is kept away from the user's one
+        env::set_var("XDG_DATA_HOME", base.join("data"));
+
+        let root = base.join(name);
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("photos")).unwrap();
+        fs::write(root.join("photos").join("cat.txt"), "meow").unwrap();
+        fs::write(root.join("photos").join("dog.txt"), "woof").unwrap();
+
+        let mut model = Model::new(root.clone(), MigrationPolicy::Ignore, &Config::default()).unwrap();
+        model.scan();
+        let root = model.root.clone();
+
+        let server = Server::bind(model, 0, TOKEN.to_owned(), read_only).unwrap();
+        let address = server.address();
+        thread::spawn(move || server.run());
+
+        Instance { root, address }
+    }
+
+    fn get(&self, target: &str) -> (u16, String) {
+        self.request("GET", target, Some(TOKEN), "")
+    }
+
+    fn post(&self, target: &str, body: &str) -> (u16, String) {
+        self.request("POST", target, Some(TOKEN), body)
+    }
+
+    fn request(&self, method: &str, target: &str, token: Option<&str>, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(self.address).unwrap();
+
+        let mut head = format!("{} {} HTTP/1.0\r\nHost: localhost\r\nContent-Length: {}\r\n",
+            method, target, body.len());
+        if let Some(token) = token {
+            head.push_str(&format!("Authorization: Bearer {}\r\n", token));
+        }
+        write!(stream, "{}\r\n{}", head, body).unwrap();
+
+        let mut response = vec![];
+        stream.read_to_end(&mut response).unwrap();
+        let response = String::from_utf8_lossy(&response).into_owned();
+
+        let status = response.split(' ').nth(1).unwrap().parse().unwrap();
+        let body = match response.find("\r\n\r\n") {
+            Some(i) => response[i + 4..].to_owned(),
+            None => String::new()
+        };
+        (status, body)
+    }
+}
+
+#[test]
+fn token_is_required() {
+    let instance = Instance::start("token", false);
+
+    assert_eq!(instance.request("GET", "/tags", None, "").0, 401);
+    assert_eq!(instance.request("GET", "/tags", Some("guess"), "").0, 401);
+    assert_eq!(instance.get("/tags"), (200, "[]".to_owned()));
+}
+
+#[test]
+fn tagged_files_are_found() {
+    let instance = Instance::start("tagging", false);
+
+    let (status, _) = instance.post("/tag", r#"{"tag": "cats", "paths": ["photos/cat.txt"]}"#);
+    assert_eq!(status, 200);
+
+    assert_eq!(instance.get("/tags"), (200, r#"[{"tag":"cats","count":1}]"#.to_owned()));
+    assert_eq!(instance.get("/tags-of?path=photos/cat.txt"), (200, r#"["cats"]"#.to_owned()));
+    assert_eq!(instance.get("/tags-of?path=photos/dog.txt"), (200, "[]".to_owned()));
+
+    let (status, body) = instance.get("/query?tag=cats");
+    assert_eq!(status, 200);
+    assert!(body.contains("cat.txt"));
+    assert!(!body.contains("dog.txt"));
+
+    let (status, _) = instance.post("/untag", r#"{"tag": "cats", "paths": ["photos/cat.txt"]}"#);
+    assert_eq!(status, 200);
+    assert_eq!(instance.get("/tags-of?path=photos/cat.txt"), (200, "[]".to_owned()));
+}
+
+#[test]
+fn read_only_server_rejects_tagging() {
+    let instance = Instance::start("read-only", true);
+
+    let (status, _) = instance.post("/tag", r#"{"tag": "cats", "paths": ["photos/cat.txt"]}"#);
+    assert_eq!(status, 403);
+    assert_eq!(instance.get("/tags"), (200, "[]".to_owned()));
+}
+
+#[test]
+fn empty_tags_are_rejected() {
+    let instance = Instance::start("empty", false);
+
+    let (status, _) = instance.post("/tag", r#"{"tag": "", "paths": ["photos/cat.txt"]}"#);
+    assert_eq!(status, 400);
+    assert_eq!(instance.get("/tags"), (200, "[]".to_owned()));
+}
+
+#[test]
+fn paths_outside_of_the_root_are_rejected() {
+    let instance = Instance::start("outside", false);
+
+    assert_eq!(instance.get("/tags-of?path=../outside").0, 400);
+    assert_eq!(instance.get("/file?path=/etc/passwd").0, 400);
+}
+
+#[test]
+fn only_regular_files_of_the_root_are_served() {
+    let instance = Instance::start("files", false);
+
+    assert_eq!(instance.get("/file?path=photos/cat.txt"), (200, "meow".to_owned()));
+    assert_eq!(instance.get("/file?path=photos").0, 404);
+    assert_eq!(instance.get("/file?path=photos/missing.txt").0, 404);
+
+    //a link inside of the root leading outside of it
+    let secret = instance.root.with_extension("secret");
+    fs::write(&secret, "password").unwrap();
+    symlink(&secret, instance.root.join("photos").join("leak.txt")).unwrap();
+    assert_eq!(instance.get("/file?path=photos/leak.txt").0, 404);
+
+    //opening a pipe would block the server forever
+    let pipe = instance.root.join("photos").join("pipe");
+    let created = process::Command::new("mkfifo").arg(&pipe).status()
+        .map(|status| status.success())
+        .unwrap_or(false);
+    if created {
+        assert_eq!(instance.get("/file?path=photos/pipe").0, 404);
+    }
+    assert_eq!(instance.get("/file?path=photos/dog.txt"), (200, "woof".to_owned()));
+}
@@ -0,0 +1,62 @@
+//files are changed behind the back of the index of a root in the temporary directory
+
+use ark_tagger::{Config, Model, MigrationPolicy};
+
+use std::env;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process;
+
+fn root(name: &str) -> PathBuf {
+    let base = env::temp_dir().join(format!("ark-tagger-verify-{}", process::id()));
+    //the registry of collections is kept away from the user's one
+    env::set_var("XDG_DATA_HOME", base.join("data"));
+
+    let root = base.join(name);
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("cat.txt"), "meow").unwrap();
+    fs::write(root.join("dog.txt"), "woof").unwrap();
+    root
+}
+
+#[test]
+fn changes_of_the_root_are_reported() {
+    let root = root("changes");
+
+    let mut model = Model::new(root, MigrationPolicy::Ignore, &Config::default()).unwrap();
+    model.scan();
+    model.index.save().unwrap();
+    let root = model.root.clone();
+
+    //a flipped byte keeps the size and the modification time
+    let cat = root.join("cat.txt");
+    let modified = fs::metadata(&cat).unwrap().modified().unwrap();
+    fs::write(&cat, "meoW").unwrap();
+    File::options().write(true).open(&cat).unwrap().set_modified(modified).unwrap();
+
+    let dog = root.join("dog.txt");
+    fs::write(&dog, "woof!").unwrap();
+
+    let model = Model::read_only(root, MigrationPolicy::Ignore, &Config::default()).unwrap();
+    let verification = model.verify().unwrap();
+    assert_eq!(verification.corrupted.len(), 1);
+    assert_eq!(verification.corrupted[0].0, cat);
+    assert_eq!(verification.modified, vec![dog]);
+    assert!(!verification.is_clean());
+}
+
+#[test]
+fn read_only_opening_marks_nothing() {
+    let root = root("read-only");
+    let config = Config::default();
+
+    let model = Model::read_only(root.clone(), MigrationPolicy::Ignore, &config).unwrap();
+    assert!(model.volume().is_none());
+    assert!(!root.join(&config.names.volume).exists());
+
+    let model = Model::new(root.clone(), MigrationPolicy::Ignore, &config).unwrap();
+    let id = model.volume().unwrap().id.clone();
+    let model = Model::read_only(root, MigrationPolicy::Ignore, &config).unwrap();
+    assert_eq!(model.volume().unwrap().id, id);
+}